@@ -0,0 +1,100 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields, Index};
+
+/// Implements `linmem::ptr::GuestLayout` for a struct mirroring a C struct compiled for wasm32.
+/// Fields are laid out in declaration order, each at the next multiple of its alignment, and the
+/// size is rounded up to the largest field alignment, as `repr(C)` does. Every field type must
+/// implement `GuestLayout` itself.
+#[proc_macro_derive(GuestLayout)]
+pub fn derive_guest_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "GuestLayout can only be derived for structs",
+        ));
+    };
+
+    let types: Vec<_> = data.fields.iter().map(|field| field.ty.clone()).collect();
+    // Bindings for the loaded fields, and how the struct is rebuilt from them
+    let bindings: Vec<_> = (0..types.len())
+        .map(|index| format_ident!("field_{}", index))
+        .collect();
+    let (construct, members) = match &data.fields {
+        Fields::Named(fields) => {
+            let names: Vec<_> = fields.named.iter().map(|field| &field.ident).collect();
+            (
+                quote! { Self { #(#names: #bindings),* } },
+                names.iter().map(|name| quote! { #name }).collect(),
+            )
+        }
+        Fields::Unnamed(_) => {
+            let indices: Vec<_> = (0..types.len()).map(Index::from).collect();
+            (
+                quote! { Self(#(#bindings),*) },
+                indices.iter().map(|index| quote! { #index }).collect(),
+            )
+        }
+        Fields::Unit => (quote! { Self }, Vec::<TokenStream2>::new()),
+    };
+
+    let where_clause = input.generics.make_where_clause();
+    for field_type in &types {
+        where_clause
+            .predicates
+            .push(parse_quote! { #field_type: ::linmem::ptr::GuestLayout });
+    }
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::linmem::ptr::GuestLayout for #name #type_generics #where_clause {
+            const ALIGN: usize = {
+                let mut align = 1;
+                #(
+                    if <#types as ::linmem::ptr::GuestLayout>::ALIGN > align {
+                        align = <#types as ::linmem::ptr::GuestLayout>::ALIGN;
+                    }
+                )*
+                align
+            };
+            const SIZE: usize = {
+                let mut offset = 0;
+                #(
+                    offset = ::linmem::ptr::align_up(offset, <#types as ::linmem::ptr::GuestLayout>::ALIGN)
+                        + <#types as ::linmem::ptr::GuestLayout>::SIZE;
+                )*
+                ::linmem::ptr::align_up(offset, Self::ALIGN)
+            };
+
+            #[allow(unused_mut, unused_assignments, unused_variables)]
+            fn load_from(memory: &::linmem::memory::LinearMemory, address: i32) -> Self {
+                let mut offset = 0;
+                #(
+                    offset = ::linmem::ptr::align_up(offset, <#types as ::linmem::ptr::GuestLayout>::ALIGN);
+                    let #bindings = <#types as ::linmem::ptr::GuestLayout>::load_from(memory, address + offset as i32);
+                    offset += <#types as ::linmem::ptr::GuestLayout>::SIZE;
+                )*
+                #construct
+            }
+
+            #[allow(unused_mut, unused_assignments, unused_variables)]
+            fn store_to(&self, memory: &mut ::linmem::memory::LinearMemory, address: i32) {
+                let mut offset = 0;
+                #(
+                    offset = ::linmem::ptr::align_up(offset, <#types as ::linmem::ptr::GuestLayout>::ALIGN);
+                    ::linmem::ptr::GuestLayout::store_to(&self.#members, memory, address + offset as i32);
+                    offset += <#types as ::linmem::ptr::GuestLayout>::SIZE;
+                )*
+            }
+        }
+    })
+}
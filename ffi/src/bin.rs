@@ -0,0 +1,102 @@
+use cbindgen::{Config, ExportConfig, Language, ParseConfig};
+use clap::{Args as ClapArgs, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Commands {
+    Header(HeaderArgs),
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+struct HeaderArgs {
+    output_path: PathBuf,
+}
+
+/// C types with a load and store helper generated for cached `MemoryDescriptor`s.
+const DIRECT_ACCESS_TYPES: [(&str, &str); 8] = [
+    ("i8", "int8_t"),
+    ("u8", "uint8_t"),
+    ("i16", "int16_t"),
+    ("u16", "uint16_t"),
+    ("i32", "int32_t"),
+    ("i64", "int64_t"),
+    ("f32", "float"),
+    ("f64", "double"),
+];
+
+/// Inline helpers for hosts that cache the base pointer, loads and stores are unchecked and
+/// assume a little endian host like the wasm memory they access.
+fn direct_access_helpers() -> String {
+    let mut helpers = String::from(
+        "static inline bool linmem_descriptor_is_current(const struct LinearMemory *memory,\n\
+         \x20                                               const MemoryDescriptor *descriptor) {\n\
+         \x20 return memory_generation(memory) == descriptor->generation;\n\
+         }\n\n\
+         static inline bool linmem_in_bounds(const MemoryDescriptor *descriptor, uint32_t address,\n\
+         \x20                                   size_t byte_count) {\n\
+         \x20 return (size_t)address <= descriptor->byte_length &&\n\
+         \x20        byte_count <= descriptor->byte_length - (size_t)address;\n\
+         }\n",
+    );
+
+    for (name, c_type) in DIRECT_ACCESS_TYPES {
+        helpers.push_str(&format!(
+            "\nstatic inline {c_type} linmem_load_{name}(const MemoryDescriptor *descriptor, uint32_t address) {{\n\
+             \x20 {c_type} value;\n\
+             \x20 memcpy(&value, descriptor->base + address, sizeof(value));\n\
+             \x20 return value;\n\
+             }}\n\n\
+             static inline void linmem_store_{name}(const MemoryDescriptor *descriptor, uint32_t address,\n\
+             \x20                                     {c_type} value) {{\n\
+             \x20 memcpy(descriptor->base + address, &value, sizeof(value));\n\
+             }}\n"
+        ));
+    }
+    helpers
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Commands::Header(args) => {
+            let config = Config {
+                language: Language::C,
+                pragma_once: true,
+                sys_includes: vec!["string.h".to_string()],
+                trailer: Some(direct_access_helpers()),
+                parse: ParseConfig {
+                    parse_deps: true,
+                    include: Some(vec!["linmem".to_string()]),
+                    ..Default::default()
+                },
+                export: ExportConfig {
+                    // Exports take these as plain integers, so nothing else pulls them in
+                    include: vec![
+                        "Trap".to_string(),
+                        "AccessKind".to_string(),
+                        "Protection".to_string(),
+                        "ShadowState".to_string(),
+                    ],
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let manifest_dir =
+                std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+            cbindgen::generate_with_config(&manifest_dir, config)
+                .expect("Unable to generate header file")
+                .write_to_file(&args.output_path);
+
+            println!("Header file generated at: {}", args.output_path.display());
+        }
+    }
+}
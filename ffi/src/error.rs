@@ -0,0 +1,70 @@
+use linmem::error::{ErrorCode, MemoryError};
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+
+struct LastError {
+    code: ErrorCode,
+    message: CString,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<LastError>> = const { RefCell::new(None) };
+}
+
+/// Records `error` as the calling thread's last error, like `errno` it is only overwritten by the
+/// next failure.
+pub(crate) fn set_last_error(error: &MemoryError) {
+    let message =
+        CString::new(error.to_string().replace('\0', " ")).expect("Nul bytes were replaced");
+    LAST_ERROR.with(|last| {
+        *last.borrow_mut() = Some(LastError {
+            code: error.code(),
+            message,
+        })
+    });
+}
+
+pub(crate) fn clear_last_error() {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+}
+
+pub(crate) fn last_error_code() -> ErrorCode {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ErrorCode::None, |error| error.code)
+    })
+}
+
+/// The message stays valid until the calling thread records another error.
+pub(crate) fn last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |error| error.message.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as ffi;
+    use crate::tests::with_memory;
+    use std::ffi::CStr;
+
+    #[test]
+    fn test_failure_sets_last_error() {
+        with_memory(2, |memory| {
+            assert_eq!(ffi::linmem_last_error(), ErrorCode::None);
+            assert!(ffi::linmem_last_error_message().is_null());
+
+            let budget = ffi::memory_budget_new(64 * 1024);
+            assert!(!unsafe { ffi::set_memory_budget(memory, budget) });
+            unsafe { ffi::memory_budget_free(budget) };
+
+            assert_eq!(ffi::linmem_last_error(), ErrorCode::LimitExceeded);
+            let message = unsafe { CStr::from_ptr(ffi::linmem_last_error_message()) };
+            assert_eq!(message.to_str().unwrap(), "memory limit exceeded");
+        });
+    }
+}
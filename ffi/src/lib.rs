@@ -0,0 +1,3078 @@
+#![allow(clippy::missing_safety_doc)]
+mod error;
+
+use linmem::arena::{ArenaAllocator, ArenaStats};
+use linmem::backing::ExternalBacking;
+use linmem::batch::BatchOp;
+use linmem::error::{ErrorCode, MemoryError};
+use linmem::iovec::IoTransfer;
+use linmem::limits::{MemoryBudget, ResourceLimiter};
+use linmem::memory::{AccessKind, GrowEvent, LinearMemory, MemoryDescriptor, Operation};
+use linmem::protect::Protection;
+use linmem::race::DataRace;
+use linmem::shadow::{ShadowState, ShadowViolation};
+use linmem::trap::Trap;
+use linmem::wait::{WaitHandle, WAIT_NOT_EQUAL, WAIT_OK, WAIT_TERMINATED};
+use std::any::Any;
+use std::ffi::{c_char, c_int, c_void};
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+pub type WatchpointCallback = extern "C" fn(
+    user_data: *mut c_void,
+    address: i32,
+    old_bytes: *const u8,
+    new_bytes: *const u8,
+    byte_count: i32,
+    operation: Operation,
+);
+
+pub type WaitCallback = extern "C" fn(user_data: *mut c_void, result: i32);
+
+/// Guest iovec resolved to host memory, laid out like POSIX `struct iovec`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct HostIovec {
+    pub base: *const u8,
+    pub length: usize,
+}
+
+pub type GrowListenerCallback = extern "C" fn(user_data: *mut c_void, event: GrowEvent);
+
+/// Resource limiter implemented by the host, called like `ResourceLimiter`. `memory_growing`
+/// returns the size in bytes the memory may grow to, `current` or less vetoes the grow. It is
+/// required, the other callbacks may be null.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ResourceLimiterCallbacks {
+    pub user_data: *mut c_void,
+    pub memory_growing:
+        Option<extern "C" fn(user_data: *mut c_void, current: usize, desired: usize) -> usize>,
+    pub memory_grow_failed:
+        Option<extern "C" fn(user_data: *mut c_void, current: usize, granted: usize)>,
+    pub memory_released: Option<extern "C" fn(user_data: *mut c_void, size: usize)>,
+}
+
+struct CallbackLimiter(ResourceLimiterCallbacks);
+
+unsafe impl Send for CallbackLimiter {}
+unsafe impl Sync for CallbackLimiter {}
+
+impl ResourceLimiter for CallbackLimiter {
+    fn memory_growing(&self, current: usize, desired: usize) -> Option<usize> {
+        // Checked by set_resource_limiter
+        let granted = self.0.memory_growing?(self.0.user_data, current, desired);
+        (granted > current).then_some(granted)
+    }
+
+    fn memory_grow_failed(&self, current: usize, granted: usize) {
+        if let Some(callback) = self.0.memory_grow_failed {
+            callback(self.0.user_data, current, granted);
+        }
+    }
+
+    fn memory_released(&self, size: usize) {
+        if let Some(callback) = self.0.memory_released {
+            callback(self.0.user_data, size);
+        }
+    }
+}
+
+/// Opaque host pointer handed back to C callbacks, the host is responsible for its thread safety.
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+/// Returned by the wait exports when the wait traps, distinct from every `WAIT_*` result.
+pub const WAIT_FAILED: i32 = -1;
+
+/// Returned by `compare` and `compare_with` when a range is out of bounds, distinct from the
+/// -1, 0 and 1 orderings.
+pub const COMPARE_FAILED: i32 = i32::MIN;
+
+/// Returned by `find_null`, `find_byte`, `find_pattern` and `string_length` when the search traps
+/// or its arguments are invalid, distinct from -1 for a search that found nothing.
+pub const SEARCH_FAILED: i32 = -2;
+
+/// Runs an export catching any panic so it never unwinds into C. The panic, or the trap raised by
+/// touching a protected page, becomes the calling thread's last error, faults the memory and
+/// `fallback` is returned in its place.
+fn guard<R>(ptr: *const LinearMemory, fallback: R, f: impl FnOnce() -> R) -> R {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        if !ptr.is_null() {
+            unsafe { (*ptr).fault() };
+        }
+        error::set_last_error(&panic_error(payload));
+        fallback
+    })
+}
+
+fn panic_error(payload: Box<dyn Any + Send>) -> MemoryError {
+    match payload.downcast::<Trap>() {
+        Ok(trap) => MemoryError::Trapped(*trap),
+        Err(payload) => MemoryError::Panicked(panic_message(payload)),
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    payload
+        .downcast::<String>()
+        .map(|message| *message)
+        .or_else(|payload| {
+            payload
+                .downcast::<&str>()
+                .map(|message| message.to_string())
+        })
+        .unwrap_or_else(|_| "unknown panic".to_string())
+}
+
+/// Length of a buffer a C caller passed, None for negative lengths or buffers too large to
+/// address.
+fn host_len<T>(len: impl TryInto<usize>) -> Option<usize> {
+    len.try_into()
+        .ok()
+        .filter(|&len| len <= isize::MAX as usize / size_of::<T>().max(1))
+}
+
+/// Slice over `len` values a C caller passed at `data`. A negative length, or a null pointer
+/// with a non-zero length, sets `InvalidRange` as the last error and returns None before any
+/// slice is built.
+unsafe fn host_slice<'a, T>(data: *const T, len: impl TryInto<usize>) -> Option<&'a [T]> {
+    match host_len::<T>(len) {
+        Some(0) => Some(&[]),
+        Some(len) if !data.is_null() => Some(unsafe { std::slice::from_raw_parts(data, len) }),
+        _ => {
+            error::set_last_error(&MemoryError::InvalidRange);
+            None
+        }
+    }
+}
+
+/// Guest range of `byte_count` bytes from `address` a C caller passed. A negative address or
+/// count, or a range ending past `i32::MAX`, sets `InvalidRange` as the last error and returns
+/// None.
+fn guest_range(address: i32, byte_count: i32) -> Option<Range<i32>> {
+    let end = (address >= 0 && byte_count >= 0)
+        .then(|| address.checked_add(byte_count))
+        .flatten();
+    if end.is_none() {
+        error::set_last_error(&MemoryError::InvalidRange);
+    }
+    end.map(|end| address..end)
+}
+
+/// Mutable counterpart of `host_slice`.
+unsafe fn host_slice_mut<'a, T>(data: *mut T, len: impl TryInto<usize>) -> Option<&'a mut [T]> {
+    match host_len::<T>(len) {
+        Some(0) => Some(&mut []),
+        Some(len) if !data.is_null() => Some(unsafe { std::slice::from_raw_parts_mut(data, len) }),
+        _ => {
+            error::set_last_error(&MemoryError::InvalidRange);
+            None
+        }
+    }
+}
+
+impl UserData {
+    fn get(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+/// Returns null if the memory could not be created, `linmem_last_error` then describes why.
+#[no_mangle]
+pub extern "C" fn alloc(pages: u32) -> *mut LinearMemory {
+    guard(
+        std::ptr::null(),
+        std::ptr::null_mut(),
+        || match LinearMemory::try_new(pages) {
+            Ok(memory) => Box::into_raw(Box::new(memory)),
+            Err(error) => {
+                error::set_last_error(&error);
+                std::ptr::null_mut()
+            }
+        },
+    )
+}
+
+/// Creates a memory over `byte_length` bytes at `base` which the host owns, it may grow in place
+/// up to `capacity` bytes. `base` must outlive the memory. Returns null if `base` is null or not
+/// 8 byte aligned, or `byte_length` exceeds `capacity`, `linmem_last_error` then describes why.
+#[no_mangle]
+pub unsafe extern "C" fn alloc_with_buffer(
+    base: *mut u8,
+    byte_length: usize,
+    capacity: usize,
+) -> *mut LinearMemory {
+    guard(std::ptr::null(), std::ptr::null_mut(), || {
+        match unsafe { ExternalBacking::new(base, byte_length, capacity) } {
+            Ok(backing) => Box::into_raw(Box::new(LinearMemory::with_backing(backing))),
+            Err(error) => {
+                error::set_last_error(&error);
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Code of the last error recorded on the calling thread, `None` if there never was one.
+#[no_mangle]
+pub extern "C" fn linmem_last_error() -> ErrorCode {
+    error::last_error_code()
+}
+
+/// Message of the last error recorded on the calling thread or null, the string is owned by
+/// linmem and stays valid until the thread records another error.
+#[no_mangle]
+pub extern "C" fn linmem_last_error_message() -> *const c_char {
+    error::last_error_message()
+}
+
+/// Clears the calling thread's last error, so a later `linmem_last_error` reports only failures
+/// after this call.
+#[no_mangle]
+pub extern "C" fn linmem_clear_last_error() {
+    error::clear_last_error();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dealloc(ptr: *mut LinearMemory) {
+    guard(std::ptr::null(), (), || {
+        if ptr.is_null() {
+            return;
+        }
+        unsafe {
+            drop(Box::from_raw(ptr));
+        }
+    })
+}
+
+/// Grows the memory by `pages`, or fewer if its resource limiter caps the request. Returns false
+/// if nothing was added, `linmem_last_error` then describes why.
+#[no_mangle]
+pub unsafe extern "C" fn grow(ptr: *mut LinearMemory, pages: u32) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory
+            .try_grow(pages)
+            .map_err(|error| error::set_last_error(&error))
+            .is_ok()
+    })
+}
+
+/// Registers `callback` to be called after every successful grow with the old and new base and
+/// size. Returns an id for `remove_grow_listener`.
+#[no_mangle]
+pub unsafe extern "C" fn add_grow_listener(
+    ptr: *mut LinearMemory,
+    callback: GrowListenerCallback,
+    user_data: *mut c_void,
+) -> u32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        let user_data = UserData(user_data);
+        memory.add_grow_listener(move |event| callback(user_data.get(), *event))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn remove_grow_listener(ptr: *mut LinearMemory, id: u32) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.remove_grow_listener(id)
+    })
+}
+
+/// Makes `limiter` decide every later grow of the memory, which is first charged its current size.
+/// Returns false if the limiter refuses that or `memory_growing` is null, `linmem_last_error` then
+/// describes why.
+#[no_mangle]
+pub unsafe extern "C" fn set_resource_limiter(
+    ptr: *mut LinearMemory,
+    limiter: ResourceLimiterCallbacks,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        if limiter.memory_growing.is_none() {
+            error::set_last_error(&MemoryError::InvalidArgument);
+            return false;
+        }
+        memory
+            .set_limiter(Arc::new(CallbackLimiter(limiter)))
+            .map_err(|error| error::set_last_error(&error))
+            .is_ok()
+    })
+}
+
+/// Creates a budget capping the total bytes of every memory it is set on. The memories keep it
+/// alive, so it may be freed as soon as it has been handed to them.
+#[no_mangle]
+pub extern "C" fn memory_budget_new(limit: usize) -> *const MemoryBudget {
+    guard(std::ptr::null(), std::ptr::null(), || {
+        Arc::into_raw(Arc::new(MemoryBudget::new(limit)))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn memory_budget_free(budget: *const MemoryBudget) {
+    guard(std::ptr::null(), (), || {
+        if !budget.is_null() {
+            drop(unsafe { Arc::from_raw(budget) });
+        }
+    })
+}
+
+/// Bytes currently held by the memories sharing `budget`.
+#[no_mangle]
+pub unsafe extern "C" fn memory_budget_used(budget: *const MemoryBudget) -> usize {
+    guard(std::ptr::null(), 0, || {
+        let budget = unsafe {
+            debug_assert!(!budget.is_null(), "MemoryBudget pointer is null");
+            &*budget
+        };
+        budget.used()
+    })
+}
+
+/// Shares `budget` with the memory, see `set_resource_limiter`.
+#[no_mangle]
+pub unsafe extern "C" fn set_memory_budget(
+    ptr: *mut LinearMemory,
+    budget: *const MemoryBudget,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        let budget = unsafe {
+            debug_assert!(!budget.is_null(), "MemoryBudget pointer is null");
+            Arc::increment_strong_count(budget);
+            Arc::from_raw(budget)
+        };
+        memory
+            .set_limiter(budget)
+            .map_err(|error| error::set_last_error(&error))
+            .is_ok()
+    })
+}
+
+/// Sets the protection of `page_count` pages from `start_page`, accesses through the exports it
+/// forbids trap and fault the memory, `protection` is a `Protection` value. Returns false if the
+/// range exceeds the memory, `protection` is not a `Protection` or it could not be applied, `linmem_last_error` then describes why. Accesses through the
+/// `memory_descriptor` base are not trapped, on mmap backings they raise `SIGSEGV` or `SIGBUS`
+/// which linmem installs no handler for.
+#[no_mangle]
+pub unsafe extern "C" fn protect(
+    ptr: *mut LinearMemory,
+    start_page: u32,
+    page_count: u32,
+    protection: u32,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        let Ok(protection) =
+            Protection::try_from(protection).map_err(|error| error::set_last_error(&error))
+        else {
+            return false;
+        };
+        let Some(end_page) = start_page.checked_add(page_count) else {
+            error::set_last_error(&MemoryError::InvalidRange);
+            return false;
+        };
+        memory
+            .protect(start_page..end_page, protection)
+            .map_err(|error| error::set_last_error(&error))
+            .is_ok()
+    })
+}
+
+/// True once an export has panicked on this memory, the panic is described by
+/// `linmem_last_error` on the thread that made the call.
+#[no_mangle]
+pub unsafe extern "C" fn is_faulted(ptr: *const LinearMemory) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.is_faulted()
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn clear_fault(ptr: *const LinearMemory) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.clear_fault()
+    })
+}
+
+/// Returns the base pointer, byte length and generation of the memory. Hosts may cache the base
+/// for as long as `memory_generation` returns the same generation.
+#[no_mangle]
+pub unsafe extern "C" fn memory_descriptor(ptr: *mut LinearMemory) -> MemoryDescriptor {
+    guard(ptr, MemoryDescriptor::default(), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.descriptor()
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn memory_generation(ptr: *const LinearMemory) -> u64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.generation()
+    })
+}
+
+/// Copies between two memories, or within one like `copy_within` when both pointers are the
+/// same. Returns false if either range exceeds its memory, `linmem_last_error` then describes why.
+#[no_mangle]
+pub unsafe extern "C" fn copy(
+    src_ptr: *const LinearMemory,
+    dest_ptr: *mut LinearMemory,
+    src_offset: i32,
+    dest_offset: i32,
+    byte_count: i32,
+) -> bool {
+    guard(dest_ptr, false, || {
+        // A copy within one memory would alias the source and destination references
+        if std::ptr::eq(src_ptr, dest_ptr) {
+            return unsafe { copy_within(dest_ptr, src_offset, dest_offset, byte_count) };
+        }
+
+        let src_memory = unsafe {
+            debug_assert!(!src_ptr.is_null(), "Source LinearMemory pointer is null");
+            &*src_ptr
+        };
+
+        let dest_memory = unsafe {
+            debug_assert!(
+                !dest_ptr.is_null(),
+                "Destination LinearMemory pointer is null"
+            );
+            &mut *dest_ptr
+        };
+
+        src_memory.copy(src_offset, dest_memory, dest_offset, byte_count);
+        true
+    })
+}
+
+/// Copies with memmove semantics inside one memory, returning false if either range exceeds the
+/// memory bounds, `linmem_last_error` then holds the trap.
+#[no_mangle]
+pub unsafe extern "C" fn copy_within(
+    ptr: *mut LinearMemory,
+    src_offset: i32,
+    dest_offset: i32,
+    byte_count: i32,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.copy_within(src_offset, dest_offset, byte_count);
+        true
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fill(ptr: *mut LinearMemory, offset: i32, byte_count: i32, value: u8) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.fill(offset, byte_count, value);
+    })
+}
+
+/// Returns the address of the first nul at or after `offset`, -1 if there is none or
+/// `SEARCH_FAILED` if the search traps, `linmem_last_error` then describes why.
+#[no_mangle]
+pub unsafe extern "C" fn find_null(ptr: *mut LinearMemory, offset: i32) -> i32 {
+    guard(ptr, SEARCH_FAILED, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.find_null(offset)
+    })
+}
+
+/// Returns the address of the first `byte` within `byte_count` bytes of `address`, returning like
+/// `find_null`. A negative address or count fails with `InvalidRange`.
+#[no_mangle]
+pub unsafe extern "C" fn find_byte(
+    ptr: *mut LinearMemory,
+    address: i32,
+    byte_count: i32,
+    byte: u8,
+) -> i32 {
+    guard(ptr, SEARCH_FAILED, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let Some(range) = guest_range(address, byte_count) else {
+            return SEARCH_FAILED;
+        };
+        memory.find_byte(address, range.len(), byte).unwrap_or(-1)
+    })
+}
+
+/// Returns the address of the first occurrence of `needle` within `byte_count` bytes of
+/// `address`, returning like `find_byte`.
+#[no_mangle]
+pub unsafe extern "C" fn find_pattern(
+    ptr: *mut LinearMemory,
+    address: i32,
+    byte_count: i32,
+    needle: *const u8,
+    needle_len: i32,
+) -> i32 {
+    guard(ptr, SEARCH_FAILED, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let Some(range) = guest_range(address, byte_count) else {
+            return SEARCH_FAILED;
+        };
+        let Some(needle) = (unsafe { host_slice(needle, needle_len) }) else {
+            return SEARCH_FAILED;
+        };
+        memory
+            .find_pattern(address, range.len(), needle)
+            .unwrap_or(-1)
+    })
+}
+
+/// Compares two ranges with `memcmp` semantics, returning -1, 0 or 1, or `COMPARE_FAILED` if a
+/// range exceeds the memory, `linmem_last_error` then describes why.
+#[no_mangle]
+pub unsafe extern "C" fn compare(
+    ptr: *mut LinearMemory,
+    address_a: i32,
+    address_b: i32,
+    byte_count: i32,
+) -> i32 {
+    guard(ptr, COMPARE_FAILED, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.compare(address_a, address_b, byte_count as usize) as i32
+    })
+}
+
+/// Compares memory at `address` with a host buffer, returning like `compare`.
+#[no_mangle]
+pub unsafe extern "C" fn compare_with(
+    ptr: *mut LinearMemory,
+    address: i32,
+    bytearray: *const u8,
+    byte_count: i32,
+) -> i32 {
+    guard(ptr, COMPARE_FAILED, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let Some(bytearray) = (unsafe { host_slice(bytearray, byte_count) }) else {
+            return COMPARE_FAILED;
+        };
+        memory.compare_with(address, bytearray) as i32
+    })
+}
+
+/// Bounded `strlen` over guest memory, named so it doesn't collide with libc's `strnlen`. Returns
+/// `SEARCH_FAILED` if the scan traps or `address` or `max_len` is negative, `linmem_last_error`
+/// then describes why.
+#[no_mangle]
+pub unsafe extern "C" fn string_length(ptr: *mut LinearMemory, address: i32, max_len: i32) -> i32 {
+    guard(ptr, SEARCH_FAILED, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let (Ok(_), Ok(max_len)) = (u32::try_from(address), usize::try_from(max_len)) else {
+            error::set_last_error(&MemoryError::InvalidRange);
+            return SEARCH_FAILED;
+        };
+        memory.strnlen(address, max_len) as i32
+    })
+}
+
+/// Scalar reads return 0 when they trap, the same as a stored zero. To tell the two apart clear
+/// the last error with `linmem_clear_last_error` before the read and check `linmem_last_error`
+/// after it, or check `is_faulted`.
+#[no_mangle]
+pub unsafe extern "C" fn read_i32(ptr: *mut LinearMemory, address: i32) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.read_i32(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_i32_from_i8(ptr: *mut LinearMemory, address: i32) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.read_i32_from_i8(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_i32_from_i16(ptr: *mut LinearMemory, address: i32) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.read_i32_from_i16(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_i32_from_u8(ptr: *mut LinearMemory, address: i32) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.read_i32_from_u8(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_i32_from_u16(ptr: *mut LinearMemory, address: i32) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.read_i32_from_u16(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn write_i32(ptr: *mut LinearMemory, address: i32, value: i32) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.write_i32(address, value);
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn write_i32_to_i8(ptr: *mut LinearMemory, address: i32, value: i32) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.write_i32_to_i8(address, value);
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn write_i32_to_i16(ptr: *mut LinearMemory, address: i32, value: i32) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.write_i32_to_i16(address, value);
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_i64(ptr: *mut LinearMemory, address: i32) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.read_i64(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_i64_from_i8(ptr: *mut LinearMemory, address: i32) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.read_i64_from_i8(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_i64_from_i16(ptr: *mut LinearMemory, address: i32) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.read_i64_from_i16(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_i64_from_i32(ptr: *mut LinearMemory, address: i32) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.read_i64_from_i32(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_i64_from_u8(ptr: *mut LinearMemory, address: i32) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.read_i64_from_u8(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_i64_from_u16(ptr: *mut LinearMemory, address: i32) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.read_i64_from_u16(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_i64_from_u32(ptr: *mut LinearMemory, address: i32) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.read_i64_from_u32(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn write_i64(ptr: *mut LinearMemory, address: i32, value: i64) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.write_i64(address, value);
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn write_i64_to_i8(ptr: *mut LinearMemory, address: i32, value: i64) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.write_i64_to_i8(address, value);
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn write_i64_to_i16(ptr: *mut LinearMemory, address: i32, value: i64) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.write_i64_to_i16(address, value);
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn write_i64_to_i32(ptr: *mut LinearMemory, address: i32, value: i64) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.write_i64_to_i32(address, value);
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_f32(ptr: *mut LinearMemory, address: i32) -> f32 {
+    guard(ptr, 0.0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.read_f32(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn write_f32(ptr: *mut LinearMemory, address: i32, value: f32) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.write_f32(address, value);
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_f64(ptr: *mut LinearMemory, address: i32) -> f64 {
+    guard(ptr, 0.0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.read_f64(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn write_f64(ptr: *mut LinearMemory, address: i32, value: f64) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.write_f64(address, value);
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_i32_array(
+    ptr: *mut LinearMemory,
+    address: i32,
+    out: *mut i32,
+    count: i32,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let Some(out) = (unsafe { host_slice_mut(out, count) }) else {
+            return false;
+        };
+        memory.read_i32_array(address, out)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn write_i32_array(
+    ptr: *mut LinearMemory,
+    address: i32,
+    values: *const i32,
+    count: i32,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        let Some(values) = (unsafe { host_slice(values, count) }) else {
+            return false;
+        };
+        memory.write_i32_array(address, values)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_i64_array(
+    ptr: *mut LinearMemory,
+    address: i32,
+    out: *mut i64,
+    count: i32,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let Some(out) = (unsafe { host_slice_mut(out, count) }) else {
+            return false;
+        };
+        memory.read_i64_array(address, out)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn write_i64_array(
+    ptr: *mut LinearMemory,
+    address: i32,
+    values: *const i64,
+    count: i32,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        let Some(values) = (unsafe { host_slice(values, count) }) else {
+            return false;
+        };
+        memory.write_i64_array(address, values)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_f32_array(
+    ptr: *mut LinearMemory,
+    address: i32,
+    out: *mut f32,
+    count: i32,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let Some(out) = (unsafe { host_slice_mut(out, count) }) else {
+            return false;
+        };
+        memory.read_f32_array(address, out)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn write_f32_array(
+    ptr: *mut LinearMemory,
+    address: i32,
+    values: *const f32,
+    count: i32,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        let Some(values) = (unsafe { host_slice(values, count) }) else {
+            return false;
+        };
+        memory.write_f32_array(address, values)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_f64_array(
+    ptr: *mut LinearMemory,
+    address: i32,
+    out: *mut f64,
+    count: i32,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let Some(out) = (unsafe { host_slice_mut(out, count) }) else {
+            return false;
+        };
+        memory.read_f64_array(address, out)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn write_f64_array(
+    ptr: *mut LinearMemory,
+    address: i32,
+    values: *const f64,
+    count: i32,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        let Some(values) = (unsafe { host_slice(values, count) }) else {
+            return false;
+        };
+        memory.write_f64_array(address, values)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_bytes(
+    memory_ptr: *const LinearMemory,
+    address: i32,
+    byte_count: i32,
+) -> *const u8 {
+    guard(memory_ptr, std::ptr::null(), || {
+        let memory = unsafe {
+            debug_assert!(!memory_ptr.is_null(), "LinearMemory pointer is null");
+            &*memory_ptr
+        };
+
+        let slice = memory.read_bytes(address, byte_count as usize);
+        slice.as_ptr()
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn write_bytes(
+    memory_ptr: *mut LinearMemory,
+    address: i32,
+    bytearray: *const u8,
+    byte_count: i32,
+) {
+    guard(memory_ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!memory_ptr.is_null(), "LinearMemory pointer is null");
+            &mut *memory_ptr
+        };
+
+        let Some(bytearray) = (unsafe { host_slice(bytearray, byte_count) }) else {
+            return;
+        };
+
+        memory.write_bytes(address, bytearray);
+    })
+}
+
+/// Resolves the `count` wasm32 iovecs at `address` into `iovecs`, which must have room for `count`
+/// entries. The pointers stay valid until the memory grows. Returns false if the array or a buffer
+/// lies outside the memory or `iovecs` is null, `linmem_last_error` then describes why.
+#[no_mangle]
+pub unsafe extern "C" fn read_iovecs(
+    ptr: *const LinearMemory,
+    address: i32,
+    count: u32,
+    iovecs: *mut HostIovec,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let Some(iovecs) = (unsafe { host_slice_mut(iovecs, count) }) else {
+            return false;
+        };
+        let buffers = match memory.read_iovecs(address, count) {
+            Ok(buffers) => buffers,
+            Err(error) => {
+                error::set_last_error(&error.into());
+                return false;
+            }
+        };
+        for (iovec, buffer) in iovecs.iter_mut().zip(buffers) {
+            *iovec = HostIovec {
+                base: buffer.as_ptr(),
+                length: buffer.len(),
+            };
+        }
+        true
+    })
+}
+
+/// Reads from `fd` straight into the guest buffers of the `count` iovecs at `address`, storing the
+/// bytes read and requested in `transfer`. Returns false if the iovecs are invalid, there are
+/// more than `IOV_MAX` of them or `readv` failed, `linmem_last_error` then describes why.
+#[cfg(unix)]
+#[no_mangle]
+pub unsafe extern "C" fn readv_from_fd(
+    ptr: *mut LinearMemory,
+    fd: c_int,
+    address: i32,
+    count: u32,
+    transfer: *mut IoTransfer,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        store_transfer(memory.readv_from_fd(fd, address, count), transfer)
+    })
+}
+
+/// Writes the guest buffers of the `count` iovecs at `address` straight to `fd`, storing the bytes
+/// written and requested in `transfer`. Returns false if the iovecs are invalid, there are
+/// more than `IOV_MAX` of them or `writev` failed, `linmem_last_error` then describes why.
+#[cfg(unix)]
+#[no_mangle]
+pub unsafe extern "C" fn writev_to_fd(
+    ptr: *const LinearMemory,
+    fd: c_int,
+    address: i32,
+    count: u32,
+    transfer: *mut IoTransfer,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        store_transfer(memory.writev_to_fd(fd, address, count), transfer)
+    })
+}
+
+#[cfg(unix)]
+fn store_transfer(result: Result<IoTransfer, MemoryError>, transfer: *mut IoTransfer) -> bool {
+    match result {
+        Ok(result) => {
+            unsafe {
+                debug_assert!(!transfer.is_null(), "Transfer pointer is null");
+                transfer.write(result)
+            };
+            true
+        }
+        Err(error) => {
+            error::set_last_error(&error);
+            false
+        }
+    }
+}
+
+/// Creates an allocator for host data in the `capacity` bytes of guest memory from `start`, to be
+/// released with `arena_destroy`. One allocator serves one memory.
+#[no_mangle]
+pub extern "C" fn arena_new(start: u32, capacity: u32) -> *mut ArenaAllocator {
+    Box::into_raw(Box::new(ArenaAllocator::new(start, capacity)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn arena_destroy(arena: *mut ArenaAllocator) {
+    guard(std::ptr::null(), (), || {
+        if !arena.is_null() {
+            drop(unsafe { Box::from_raw(arena) });
+        }
+    })
+}
+
+/// Allocates `size` bytes aligned to `align` in `memory`, growing it when needed. Returns 0 if the
+/// region is exhausted or the memory could not grow, `linmem_last_error` then describes why.
+#[no_mangle]
+pub unsafe extern "C" fn arena_alloc(
+    arena: *mut ArenaAllocator,
+    ptr: *mut LinearMemory,
+    size: u32,
+    align: u32,
+) -> u32 {
+    guard(ptr, 0, || {
+        let (arena, memory) = unsafe {
+            debug_assert!(!arena.is_null(), "ArenaAllocator pointer is null");
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            (&mut *arena, &mut *ptr)
+        };
+        arena.alloc(memory, size, align).unwrap_or_else(|error| {
+            error::set_last_error(&error);
+            0
+        })
+    })
+}
+
+/// Returns false if `address` is not a live allocation of `arena`.
+#[no_mangle]
+pub unsafe extern "C" fn arena_free(arena: *mut ArenaAllocator, address: u32) -> bool {
+    guard(std::ptr::null(), false, || {
+        let arena = unsafe {
+            debug_assert!(!arena.is_null(), "ArenaAllocator pointer is null");
+            &mut *arena
+        };
+        arena
+            .free(address)
+            .map_err(|error| error::set_last_error(&error))
+            .is_ok()
+    })
+}
+
+/// Resizes the allocation at `address`, moving it if it can't grow in place. Returns 0 on failure
+/// with the allocation left as it was, `linmem_last_error` then describes why.
+#[no_mangle]
+pub unsafe extern "C" fn arena_realloc(
+    arena: *mut ArenaAllocator,
+    ptr: *mut LinearMemory,
+    address: u32,
+    size: u32,
+    align: u32,
+) -> u32 {
+    guard(ptr, 0, || {
+        let (arena, memory) = unsafe {
+            debug_assert!(!arena.is_null(), "ArenaAllocator pointer is null");
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            (&mut *arena, &mut *ptr)
+        };
+        arena
+            .realloc(memory, address, size, align)
+            .unwrap_or_else(|error| {
+                error::set_last_error(&error);
+                0
+            })
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn arena_stats(arena: *const ArenaAllocator) -> ArenaStats {
+    guard(std::ptr::null(), ArenaStats::default(), || {
+        let arena = unsafe {
+            debug_assert!(!arena.is_null(), "ArenaAllocator pointer is null");
+            &*arena
+        };
+        arena.stats()
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_read_i32(ptr: *mut LinearMemory, address: i32) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_read_i32(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_read_i32_from_i8(ptr: *mut LinearMemory, address: i32) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_read_i32_from_i8(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_read_i32_from_i16(ptr: *mut LinearMemory, address: i32) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_read_i32_from_i16(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_read_i32_from_u8(ptr: *mut LinearMemory, address: i32) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_read_i32_from_u8(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_read_i32_from_u16(ptr: *mut LinearMemory, address: i32) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_read_i32_from_u16(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_write_i32(ptr: *mut LinearMemory, address: i32, value: i32) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.atomic_write_i32(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_write_i32_to_i8(ptr: *mut LinearMemory, address: i32, value: i32) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.atomic_write_i32_to_i8(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_write_i32_to_i16(ptr: *mut LinearMemory, address: i32, value: i32) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.atomic_write_i32_to_i16(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_read_i64(ptr: *mut LinearMemory, address: i32) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_read_i64(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_read_i64_from_i8(ptr: *mut LinearMemory, address: i32) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_read_i64_from_i8(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_read_i64_from_i16(ptr: *mut LinearMemory, address: i32) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_read_i64_from_i16(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_read_i64_from_i32(ptr: *mut LinearMemory, address: i32) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_read_i64_from_i32(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_read_i64_from_u8(ptr: *mut LinearMemory, address: i32) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_read_i64_from_u8(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_read_i64_from_u16(ptr: *mut LinearMemory, address: i32) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_read_i64_from_u16(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_read_i64_from_u32(ptr: *mut LinearMemory, address: i32) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_read_i64_from_u32(address)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_write_i64(ptr: *mut LinearMemory, address: i32, value: i64) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.atomic_write_i64(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_write_i64_to_i8(ptr: *mut LinearMemory, address: i32, value: i64) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.atomic_write_i64_to_i8(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_write_i64_to_i16(ptr: *mut LinearMemory, address: i32, value: i64) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.atomic_write_i64_to_i16(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_write_i64_to_i32(ptr: *mut LinearMemory, address: i32, value: i64) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.atomic_write_i64_to_i32(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_add_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_add_i32(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_and_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_and_i32(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_sub_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_sub_i32(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_or_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_or_i32(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_xor_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_xor_i32(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_exchange_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_exchange_i32(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_add_i32_to_i8(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_add_i32_to_i8(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_and_i32_to_i8(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_and_i32_to_i8(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_sub_i32_to_i8(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_sub_i32_to_i8(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_or_i32_to_i8(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_or_i32_to_i8(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_xor_i32_to_i8(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_xor_i32_to_i8(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_exchange_i32_to_i8(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_exchange_i32_to_i8(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_add_i32_to_i16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_add_i32_to_i16(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_and_i32_to_i16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_and_i32_to_i16(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_sub_i32_to_i16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_sub_i32_to_i16(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_or_i32_to_i16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_or_i32_to_i16(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_xor_i32_to_i16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_xor_i32_to_i16(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_exchange_i32_to_i16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_exchange_i32_to_i16(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_add_i64(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_add_i64(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_and_i64(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_and_i64(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_sub_i64(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_sub_i64(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_or_i64(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_or_i64(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_xor_i64(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_xor_i64(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_exchange_i64(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_exchange_i64(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_add_i64_to_i8(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_add_i64_to_i8(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_and_i64_to_i8(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_and_i64_to_i8(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_sub_i64_to_i8(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_sub_i64_to_i8(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_or_i64_to_i8(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_or_i64_to_i8(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_xor_i64_to_i8(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_xor_i64_to_i8(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_exchange_i64_to_i8(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_exchange_i64_to_i8(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_add_i64_to_i16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_add_i64_to_i16(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_and_i64_to_i16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_and_i64_to_i16(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_sub_i64_to_i16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_sub_i64_to_i16(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_or_i64_to_i16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_or_i64_to_i16(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_xor_i64_to_i16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_xor_i64_to_i16(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_exchange_i64_to_i16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_exchange_i64_to_i16(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_add_i64_to_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_add_i64_to_i32(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_and_i64_to_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_and_i64_to_i32(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_sub_i64_to_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_sub_i64_to_i32(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_or_i64_to_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_or_i64_to_i32(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_xor_i64_to_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_xor_i64_to_i32(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_rmw_exchange_i64_to_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    value: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_rmw_exchange_i64_to_i32(address, value)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_compare_exchange_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    current: i32,
+    new: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_compare_exchange_i32(address, current, new)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_compare_exchange_i32_to_i8(
+    ptr: *mut LinearMemory,
+    address: i32,
+    current: i32,
+    new: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_compare_exchange_i32_to_i8(address, current, new)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_compare_exchange_i32_to_i16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    current: i32,
+    new: i32,
+) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_compare_exchange_i32_to_i16(address, current, new)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_compare_exchange_i64(
+    ptr: *mut LinearMemory,
+    address: i32,
+    current: i64,
+    new: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_compare_exchange_i64(address, current, new)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_compare_exchange_i64_to_i8(
+    ptr: *mut LinearMemory,
+    address: i32,
+    current: i64,
+    new: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_compare_exchange_i64_to_i8(address, current, new)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_compare_exchange_i64_to_i16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    current: i64,
+    new: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_compare_exchange_i64_to_i16(address, current, new)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_compare_exchange_i64_to_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    current: i64,
+    new: i64,
+) -> i64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_compare_exchange_i64_to_i32(address, current, new)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atomic_fence(ptr: *mut LinearMemory) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.atomic_fence()
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn notify(ptr: *mut LinearMemory, address: i32, count: i32) -> i32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.notify(address, count)
+    })
+}
+
+/// Blocks until notified or `timeout` nanoseconds pass, a negative timeout waits forever. Returns
+/// 0 when woken, 1 if `address` does not hold `expected`, 2 on timeout, 3 once the waiters are
+/// terminated, or -1 if the wait trapped, `linmem_last_error` then describes why.
+#[no_mangle]
+pub unsafe extern "C" fn wait_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    expected: i32,
+    timeout: i64,
+) -> i32 {
+    guard(ptr, WAIT_FAILED, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.wait_i32(address, expected, timeout)
+    })
+}
+
+/// 64 bit counterpart of `wait_i32`.
+#[no_mangle]
+pub unsafe extern "C" fn wait_i64(
+    ptr: *mut LinearMemory,
+    address: i32,
+    expected: i64,
+    timeout: i64,
+) -> i32 {
+    guard(ptr, WAIT_FAILED, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.wait_i64(address, expected, timeout)
+    })
+}
+
+/// Queues `callback` to run with the wait result on the notifying thread instead of blocking.
+/// Returns 0 once queued, 1 without queueing if `address` does not hold `expected`, or -1 if the
+/// wait trapped, `linmem_last_error` then describes why. A non-null `handle` receives a handle for
+/// `wait_handle_cancel`, to be released with `wait_handle_free`. A concurrent `notify` may run
+/// the callback before this returns. Once waiters are terminated the callback has already run
+/// with 3 when this returns 3, and no handle is written.
+#[no_mangle]
+pub unsafe extern "C" fn wait_async_i32(
+    ptr: *mut LinearMemory,
+    address: i32,
+    expected: i32,
+    callback: WaitCallback,
+    user_data: *mut c_void,
+    handle: *mut *mut WaitHandle,
+) -> i32 {
+    guard(ptr, WAIT_FAILED, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let user_data = UserData(user_data);
+        let Some(wait) = memory.wait_callback_i32(address, expected, move |result| {
+            callback(user_data.get(), result)
+        }) else {
+            return WAIT_NOT_EQUAL;
+        };
+        unsafe { queued_wait(wait, handle) }
+    })
+}
+
+/// 64 bit counterpart of `wait_async_i32`.
+#[no_mangle]
+pub unsafe extern "C" fn wait_async_i64(
+    ptr: *mut LinearMemory,
+    address: i32,
+    expected: i64,
+    callback: WaitCallback,
+    user_data: *mut c_void,
+    handle: *mut *mut WaitHandle,
+) -> i32 {
+    guard(ptr, WAIT_FAILED, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let user_data = UserData(user_data);
+        let Some(wait) = memory.wait_callback_i64(address, expected, move |result| {
+            callback(user_data.get(), result)
+        }) else {
+            return WAIT_NOT_EQUAL;
+        };
+        unsafe { queued_wait(wait, handle) }
+    })
+}
+
+/// Hands `wait` out through `handle` unless waiters were terminated while it was registered, in
+/// which case its callback already ran.
+unsafe fn queued_wait(wait: WaitHandle, handle: *mut *mut WaitHandle) -> i32 {
+    if wait.result() == Some(WAIT_TERMINATED) {
+        return WAIT_TERMINATED;
+    }
+    if !handle.is_null() {
+        unsafe { handle.write(Box::into_raw(Box::new(wait))) };
+    }
+    WAIT_OK
+}
+
+/// Wakes every waiter with the terminated result 3, later waits return it at once. `dealloc` does
+/// this itself before freeing the memory.
+#[no_mangle]
+pub unsafe extern "C" fn terminate_waiters(ptr: *mut LinearMemory) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.terminate_waiters()
+    })
+}
+
+/// Cancels a wait queued by `wait_async_*`, true if it was still pending and its callback will
+/// never run.
+#[no_mangle]
+pub unsafe extern "C" fn wait_handle_cancel(handle: *const WaitHandle) -> bool {
+    guard(std::ptr::null(), false, || {
+        let handle = unsafe {
+            debug_assert!(!handle.is_null(), "WaitHandle pointer is null");
+            &*handle
+        };
+        handle.cancel()
+    })
+}
+
+/// Releases a handle without cancelling its wait.
+#[no_mangle]
+pub unsafe extern "C" fn wait_handle_free(handle: *mut WaitHandle) {
+    guard(std::ptr::null(), (), || {
+        if !handle.is_null() {
+            drop(unsafe { Box::from_raw(handle) });
+        }
+    })
+}
+
+/// Watches accesses of `kind`, an `AccessKind` value, to a range. Returns the id of the new
+/// watchpoint, or 0 if the range or kind is invalid, `linmem_last_error` then describes why.
+#[no_mangle]
+pub unsafe extern "C" fn add_watchpoint(
+    ptr: *mut LinearMemory,
+    address: i32,
+    byte_count: i32,
+    kind: u32,
+    callback: WatchpointCallback,
+    user_data: *mut c_void,
+) -> u32 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let Ok(kind) = AccessKind::try_from(kind).map_err(|error| error::set_last_error(&error))
+        else {
+            return 0;
+        };
+        let Some(range) = guest_range(address, byte_count) else {
+            return 0;
+        };
+        let user_data = UserData(user_data);
+        memory.add_watchpoint(range, kind, move |event| {
+            callback(
+                user_data.get(),
+                event.address,
+                event.old.as_ptr(),
+                event.new.as_ptr(),
+                event.new.len() as i32,
+                event.operation,
+            )
+        })
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn remove_watchpoint(ptr: *mut LinearMemory, id: u32) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.remove_watchpoint(id)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enable_shadow(ptr: *mut LinearMemory) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.enable_shadow()
+    })
+}
+
+/// Marks a range in the shadow map with `state`, a `ShadowState` value. Returns false if the range
+/// or state is invalid, `linmem_last_error` then describes why.
+#[no_mangle]
+pub unsafe extern "C" fn poison(
+    ptr: *mut LinearMemory,
+    address: i32,
+    byte_count: i32,
+    state: u8,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let Ok(state) = ShadowState::try_from(state).map_err(|error| error::set_last_error(&error))
+        else {
+            return false;
+        };
+        let Some(range) = guest_range(address, byte_count) else {
+            return false;
+        };
+        memory.poison(range, state);
+        true
+    })
+}
+
+/// Marks a range addressable again, returning like `poison`.
+#[no_mangle]
+pub unsafe extern "C" fn unpoison(ptr: *mut LinearMemory, address: i32, byte_count: i32) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let Some(range) = guest_range(address, byte_count) else {
+            return false;
+        };
+        memory.unpoison(range);
+        true
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn take_shadow_violation(
+    ptr: *mut LinearMemory,
+    violation: *mut ShadowViolation,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        debug_assert!(!violation.is_null(), "Violation pointer is null");
+
+        match memory.take_shadow_violation() {
+            Some(taken) => {
+                unsafe { violation.write(taken) };
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+/// Number of shadow violations discarded because the queue `take_shadow_violation` drains was full.
+#[no_mangle]
+pub unsafe extern "C" fn dropped_shadow_violations(ptr: *mut LinearMemory) -> u64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.dropped_shadow_violations()
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enable_race_detector(ptr: *mut LinearMemory) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        memory.enable_race_detector()
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn race_thread_id() -> u32 {
+    linmem::race::current_thread_id()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn race_release(ptr: *mut LinearMemory, key: u64) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.race_release(key)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn race_acquire(ptr: *mut LinearMemory, key: u64) {
+    guard(ptr, (), || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.race_acquire(key)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn take_data_race(ptr: *mut LinearMemory, race: *mut DataRace) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        debug_assert!(!race.is_null(), "Data race pointer is null");
+
+        match memory.take_data_race() {
+            Some(taken) => {
+                unsafe { race.write(taken) };
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+/// Number of data races discarded because the queue `take_data_race` drains was full.
+#[no_mangle]
+pub unsafe extern "C" fn dropped_data_races(ptr: *mut LinearMemory) -> u64 {
+    guard(ptr, 0, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        memory.dropped_data_races()
+    })
+}
+
+/// Decodes `byte_count` bytes of UTF-8 at `address` into `out` as UTF-16, replacing invalid
+/// sequences with U+FFFD. `out` needs room for `byte_count` code units, the number of units
+/// written is returned. Returns -1 and sets the last error if the range is out of bounds,
+/// `byte_count` is negative or `out` is null.
+#[no_mangle]
+pub unsafe extern "C" fn read_utf8_to_utf16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    byte_count: i32,
+    out: *mut u16,
+) -> i32 {
+    guard(ptr, -1, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let Some(out) = (unsafe { host_slice_mut(out, byte_count) }) else {
+            return -1;
+        };
+
+        let Ok(string) = memory.read_utf8_lossy(address, out.len()) else {
+            error::set_last_error(&MemoryError::InvalidRange);
+            return -1;
+        };
+        // Every byte decodes to at most one code unit, so the units always fit
+        let mut written = 0;
+        for (slot, unit) in out.iter_mut().zip(string.encode_utf16()) {
+            *slot = unit;
+            written += 1;
+        }
+        written
+    })
+}
+
+/// Copies `code_units` little endian UTF-16 code units at `address` into `out`. Returns false
+/// and sets the last error if the range is out of bounds, `code_units` is negative or `out` is
+/// null.
+#[no_mangle]
+pub unsafe extern "C" fn read_utf16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    code_units: i32,
+    out: *mut u16,
+) -> bool {
+    guard(ptr, false, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &*ptr
+        };
+        let Some(out) = (unsafe { host_slice_mut(out, code_units) }) else {
+            return false;
+        };
+
+        let Ok(units) = memory.read_code_units(address, out.len()) else {
+            error::set_last_error(&MemoryError::InvalidRange);
+            return false;
+        };
+        for (slot, unit) in out.iter_mut().zip(units) {
+            *slot = unit;
+        }
+        true
+    })
+}
+
+/// Encodes host UTF-16 as UTF-8 at `address`, returning the bytes written or -1 if the string
+/// would exceed the memory bounds or `code_units` is negative. Unpaired surrogates are replaced
+/// with U+FFFD.
+#[no_mangle]
+pub unsafe extern "C" fn write_utf16_as_utf8(
+    ptr: *mut LinearMemory,
+    address: i32,
+    units: *const u16,
+    code_units: i32,
+    nul_terminated: bool,
+) -> i32 {
+    guard(ptr, -1, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        let Some(units) = (unsafe { host_slice(units, code_units) }) else {
+            return -1;
+        };
+
+        let string = String::from_utf16_lossy(units);
+        memory
+            .write_utf8(address, &string, nul_terminated)
+            .map_or(-1, |written| written as i32)
+    })
+}
+
+/// Writes host UTF-16 code units at `address` as little endian UTF-16, returning the code units
+/// written or -1 if they would exceed the memory bounds or `code_units` is negative.
+#[no_mangle]
+pub unsafe extern "C" fn write_utf16(
+    ptr: *mut LinearMemory,
+    address: i32,
+    units: *const u16,
+    code_units: i32,
+    nul_terminated: bool,
+) -> i32 {
+    guard(ptr, -1, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        let Some(units) = (unsafe { host_slice(units, code_units) }) else {
+            return -1;
+        };
+
+        memory
+            .write_code_units(address, units, nul_terminated)
+            .map_or(-1, |written| written as i32)
+    })
+}
+
+/// Runs `ops_len` instructions writing one result per instruction to `results`. Returns -1 when the
+/// whole batch ran, otherwise the index of the first trapping instruction with its `Trap` code
+/// stored at the same index of `results`, or `ops_len` if it panicked. Returns -2 without running
+/// anything if `ops_len` is negative or a pointer is null, `linmem_last_error` then describes why.
+/// Opcodes are the `BatchOpcode` discriminants.
+#[no_mangle]
+pub unsafe extern "C" fn execute_batch(
+    ptr: *mut LinearMemory,
+    ops: *const BatchOp,
+    ops_len: i32,
+    results: *mut i64,
+) -> i32 {
+    guard(ptr, ops_len, || {
+        let memory = unsafe {
+            debug_assert!(!ptr.is_null(), "LinearMemory pointer is null");
+            &mut *ptr
+        };
+        let (Some(ops), Some(results)) = (unsafe { host_slice(ops, ops_len) }, unsafe {
+            host_slice_mut(results, ops_len)
+        }) else {
+            return -2;
+        };
+
+        match memory.execute_batch(ops, results) {
+            Ok(()) => -1,
+            Err(trap) => {
+                results[trap.index] = trap.trap as i64;
+                trap.index as i32
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::ffi::CStr;
+    use std::thread;
+
+    const PAGE: i32 = 64 * 1024;
+
+    /// Runs `test` against a memory of `pages` pages on a thread of its own, so the last error it
+    /// sees is its own, and deallocates the memory afterwards.
+    pub(crate) fn with_memory(pages: u32, test: impl FnOnce(*mut LinearMemory) + Send + 'static) {
+        thread::spawn(move || {
+            let memory = alloc(pages);
+            assert!(!memory.is_null());
+            test(memory);
+            unsafe { dealloc(memory) };
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn test_panic_is_contained() {
+        with_memory(1, |memory| {
+            assert_eq!(guard(memory, -1, || panic!("contained")), -1);
+
+            assert!(unsafe { is_faulted(memory) });
+            assert_eq!(linmem_last_error(), ErrorCode::Panic);
+            let message = unsafe { CStr::from_ptr(linmem_last_error_message()) };
+            assert_eq!(message.to_str().unwrap(), "panicked: contained");
+
+            unsafe { clear_fault(memory) };
+            assert!(!unsafe { is_faulted(memory) });
+            unsafe { fill(memory, PAGE - 4, 16, 0xFF) };
+            assert!(unsafe { is_faulted(memory) });
+            assert_eq!(linmem_last_error(), ErrorCode::Trapped);
+        });
+    }
+
+    #[test]
+    fn test_failures_return_sentinels() {
+        with_memory(1, |memory| {
+            assert_eq!(unsafe { wait_i32(memory, PAGE, 0, 0) }, WAIT_FAILED);
+            assert_eq!(unsafe { compare(memory, 0, PAGE - 2, 4) }, COMPARE_FAILED);
+            assert_eq!(
+                unsafe { compare_with(memory, 0, std::ptr::null(), -1) },
+                COMPARE_FAILED
+            );
+            unsafe { clear_fault(memory) };
+
+            assert_eq!(unsafe { find_null(memory, PAGE + 1) }, SEARCH_FAILED);
+            assert_eq!(unsafe { find_byte(memory, 0, 16, 1) }, -1);
+            assert_eq!(unsafe { find_byte(memory, 0, -1, 0) }, SEARCH_FAILED);
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidRange);
+            assert_eq!(
+                unsafe { find_pattern(memory, PAGE - 2, 4, b"ab".as_ptr(), 2) },
+                SEARCH_FAILED
+            );
+            assert_eq!(unsafe { string_length(memory, 0, -1) }, SEARCH_FAILED);
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidRange);
+            assert_eq!(unsafe { string_length(memory, PAGE + 1, 4) }, SEARCH_FAILED);
+            unsafe { clear_fault(memory) };
+
+            // A trapped read is only told apart from a stored zero by the last error
+            linmem_clear_last_error();
+            assert_eq!(unsafe { read_i32(memory, 0) }, 0);
+            assert_eq!(linmem_last_error(), ErrorCode::None);
+            assert_eq!(unsafe { read_i32(memory, PAGE - 2) }, 0);
+            assert_eq!(linmem_last_error(), ErrorCode::Trapped);
+            assert!(unsafe { is_faulted(memory) });
+        });
+    }
+
+    #[test]
+    fn test_copy_reports_out_of_bounds() {
+        with_memory(1, |memory| {
+            let other = alloc(1);
+            unsafe { write_i32(memory, 0, 7) };
+
+            assert!(unsafe { copy(memory, other, 0, 4, 4) });
+            assert_eq!(unsafe { read_i32(other, 4) }, 7);
+            assert!(unsafe { copy(memory, memory, 0, 8, 4) });
+            assert_eq!(unsafe { read_i32(memory, 8) }, 7);
+
+            assert!(!unsafe { copy(memory, memory, 0, PAGE - 2, 4) });
+            assert_eq!(linmem_last_error(), ErrorCode::Trapped);
+            unsafe { clear_fault(memory) };
+            assert!(!unsafe { copy(memory, other, 0, PAGE - 2, 4) });
+            assert_eq!(linmem_last_error(), ErrorCode::Trapped);
+
+            unsafe { dealloc(other) };
+        });
+    }
+
+    extern "C" fn ignore_watch_event(
+        _: *mut c_void,
+        _: i32,
+        _: *const u8,
+        _: *const u8,
+        _: i32,
+        _: Operation,
+    ) {
+    }
+
+    #[test]
+    fn test_overflowing_guest_ranges_are_rejected() {
+        with_memory(1, |memory| {
+            let watch = |address, byte_count| unsafe {
+                add_watchpoint(
+                    memory,
+                    address,
+                    byte_count,
+                    AccessKind::Write as u32,
+                    ignore_watch_event,
+                    std::ptr::null_mut(),
+                )
+            };
+            assert_ne!(watch(0, 4), 0);
+            assert_eq!(watch(i32::MAX - 2, 4), 0);
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidRange);
+
+            unsafe { enable_shadow(memory) };
+            let freed = ShadowState::Freed as u8;
+            assert!(unsafe { poison(memory, 0, 4, freed) });
+            assert!(!unsafe { poison(memory, i32::MAX, 1, freed) });
+            assert!(!unsafe { unpoison(memory, 0, -4) });
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidRange);
+        });
+    }
+
+    #[test]
+    fn test_invalid_enum_values_are_rejected() {
+        with_memory(1, |memory| {
+            let watchpoint = unsafe {
+                add_watchpoint(memory, 0, 4, 0, ignore_watch_event, std::ptr::null_mut())
+            };
+            assert_eq!(watchpoint, 0);
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidArgument);
+
+            unsafe { enable_shadow(memory) };
+            assert!(!unsafe { poison(memory, 0, 4, 4) });
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidArgument);
+            assert!(!unsafe { is_faulted(memory) });
+        });
+    }
+
+    #[test]
+    fn test_protected_write_traps() {
+        with_memory(2, |memory| {
+            assert!(unsafe { protect(memory, 1, 1, Protection::ReadOnly as u32) });
+            unsafe { write_i32(memory, PAGE + 8, 1) };
+
+            assert!(unsafe { is_faulted(memory) });
+            assert_eq!(linmem_last_error(), ErrorCode::Trapped);
+            assert_eq!(unsafe { read_i32(memory, PAGE + 8) }, 0);
+
+            assert!(!unsafe { protect(memory, 1, u32::MAX, Protection::NoAccess as u32) });
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidRange);
+            assert!(!unsafe { protect(memory, 1, 1, 2) });
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidArgument);
+        });
+    }
+
+    #[test]
+    fn test_memory_budget() {
+        with_memory(2, |first| {
+            let budget = memory_budget_new(3 * PAGE as usize);
+            let second = alloc(2);
+
+            assert!(unsafe { set_memory_budget(first, budget) });
+            assert!(!unsafe { set_memory_budget(second, budget) });
+            assert_eq!(linmem_last_error(), ErrorCode::LimitExceeded);
+            unsafe { memory_budget_free(budget) };
+
+            assert!(unsafe { grow(first, 1) });
+            assert!(!unsafe { grow(first, 1) });
+            assert_eq!(linmem_last_error(), ErrorCode::LimitExceeded);
+
+            unsafe { dealloc(second) };
+        });
+    }
+
+    #[test]
+    fn test_host_buffers_are_validated() {
+        with_memory(1, |memory| {
+            let mut out = [0i32; 2];
+            assert!(!unsafe { read_i32_array(memory, 0, out.as_mut_ptr(), -1) });
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidRange);
+            assert!(!unsafe { write_i64_array(memory, 0, std::ptr::null(), 2) });
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidRange);
+            assert_eq!(
+                unsafe { write_utf16(memory, 0, std::ptr::null(), 4, false) },
+                -1
+            );
+            assert_eq!(
+                unsafe { execute_batch(memory, std::ptr::null(), -3, std::ptr::null_mut()) },
+                -2
+            );
+
+            let mut buffer = [0u64; 2];
+            let misaligned = unsafe { buffer.as_mut_ptr().cast::<u8>().add(4) };
+            assert!(unsafe { alloc_with_buffer(misaligned, 0, 8) }.is_null());
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidArgument);
+
+            // Empty buffers may be null
+            assert!(unsafe { read_i32_array(memory, 0, std::ptr::null_mut(), 0) });
+            assert_eq!(
+                unsafe { find_pattern(memory, 0, 16, std::ptr::null(), 0) },
+                0
+            );
+        });
+    }
+
+    #[test]
+    fn test_utf16_reads_validate_buffers() {
+        with_memory(1, |memory| {
+            unsafe { write_bytes(memory, 0, "hé".as_ptr(), 3) };
+            let mut out = [0u16; 3];
+
+            assert_eq!(
+                unsafe { read_utf8_to_utf16(memory, 0, 3, out.as_mut_ptr()) },
+                2
+            );
+            assert_eq!(&out[..2], &[u16::from(b'h'), 0xE9]);
+            assert_eq!(
+                unsafe { read_utf8_to_utf16(memory, 0, -1, out.as_mut_ptr()) },
+                -1
+            );
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidRange);
+            linmem_clear_last_error();
+            assert_eq!(
+                unsafe { read_utf8_to_utf16(memory, PAGE - 1, 3, out.as_mut_ptr()) },
+                -1
+            );
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidRange);
+
+            assert!(unsafe { read_utf16(memory, 0, 1, out.as_mut_ptr()) });
+            assert!(!unsafe { read_utf16(memory, 0, 2, std::ptr::null_mut()) });
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidRange);
+            linmem_clear_last_error();
+            assert!(!unsafe { read_utf16(memory, PAGE - 2, 3, out.as_mut_ptr()) });
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidRange);
+        });
+    }
+
+    #[test]
+    fn test_read_iovecs() {
+        with_memory(1, |memory| {
+            unsafe {
+                write_bytes(memory, 64, b"iovec".as_ptr(), 5);
+                write_i32(memory, 0, 64);
+                write_i32(memory, 4, 5);
+            }
+            let mut iovecs = [HostIovec {
+                base: std::ptr::null(),
+                length: 0,
+            }];
+
+            assert!(unsafe { read_iovecs(memory, 0, 1, iovecs.as_mut_ptr()) });
+            let buffer = unsafe { std::slice::from_raw_parts(iovecs[0].base, iovecs[0].length) };
+            assert_eq!(buffer, b"iovec");
+
+            unsafe { write_i32(memory, 4, PAGE) };
+            assert!(!unsafe { read_iovecs(memory, 0, 1, iovecs.as_mut_ptr()) });
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidRange);
+        });
+    }
+
+    #[test]
+    fn test_arena() {
+        with_memory(1, |memory| {
+            let page = PAGE as u32;
+            let arena = arena_new(page, page);
+
+            let address = unsafe { arena_alloc(arena, memory, 100, 8) };
+            assert_eq!(address, page);
+            assert_eq!(
+                unsafe { memory_descriptor(memory) }.byte_length,
+                2 * page as usize
+            );
+            assert_eq!(unsafe { arena_alloc(arena, memory, page, 8) }, 0);
+            assert_eq!(linmem_last_error(), ErrorCode::LimitExceeded);
+
+            let address = unsafe { arena_realloc(arena, memory, address, 200, 8) };
+            let stats = unsafe { arena_stats(arena) };
+            assert_eq!((stats.allocated_bytes, stats.allocation_count), (200, 1));
+            assert!(unsafe { arena_free(arena, address) });
+            assert!(!unsafe { arena_free(arena, address) });
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidRange);
+
+            unsafe { arena_destroy(arena) };
+        });
+    }
+
+    #[test]
+    fn test_resource_limiter_callbacks() {
+        extern "C" fn allow_one_page(_: *mut c_void, _: usize, desired: usize) -> usize {
+            desired.min(PAGE as usize)
+        }
+
+        with_memory(0, |memory| {
+            let mut limiter = ResourceLimiterCallbacks {
+                user_data: std::ptr::null_mut(),
+                memory_growing: None,
+                memory_grow_failed: None,
+                memory_released: None,
+            };
+            assert!(!unsafe { set_resource_limiter(memory, limiter) });
+            assert_eq!(linmem_last_error(), ErrorCode::InvalidArgument);
+
+            limiter.memory_growing = Some(allow_one_page);
+            assert!(unsafe { set_resource_limiter(memory, limiter) });
+            assert!(unsafe { grow(memory, 1) });
+            assert!(!unsafe { grow(memory, 1) });
+        });
+    }
+
+    #[test]
+    fn test_wait_async_callback() {
+        extern "C" fn record(user_data: *mut c_void, result: i32) {
+            unsafe { *user_data.cast::<i32>() = result };
+        }
+
+        with_memory(1, |memory| {
+            let mut result = -1;
+            let user_data = (&mut result as *mut i32).cast();
+            let mut handle = std::ptr::null_mut();
+
+            let queued = unsafe { wait_async_i32(memory, 0, 0, record, user_data, &mut handle) };
+            assert_eq!(queued, 0);
+            assert_eq!(unsafe { notify(memory, 0, 1) }, 1);
+            assert_eq!(result, 0);
+            assert!(!unsafe { wait_handle_cancel(handle) });
+            unsafe { wait_handle_free(handle) };
+
+            let not_equal =
+                unsafe { wait_async_i64(memory, 0, 1, record, user_data, std::ptr::null_mut()) };
+            assert_eq!(not_equal, 1);
+
+            unsafe { terminate_waiters(memory) };
+            let mut handle = std::ptr::null_mut();
+            let terminated =
+                unsafe { wait_async_i64(memory, 0, 0, record, user_data, &mut handle) };
+            assert_eq!(terminated, WAIT_TERMINATED);
+            assert_eq!(result, WAIT_TERMINATED);
+            assert!(handle.is_null());
+        });
+    }
+}
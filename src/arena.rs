@@ -0,0 +1,363 @@
+use alloc::collections::BTreeMap;
+
+use crate::error::MemoryError;
+use crate::memory::{LinearMemory, PAGE_SIZE};
+
+/// Snapshot of an `ArenaAllocator`'s bookkeeping.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ArenaStats {
+    /// Bytes held by live allocations
+    pub allocated_bytes: usize,
+    pub allocation_count: usize,
+    /// Most bytes live at once
+    pub peak_allocated_bytes: usize,
+    /// Bytes of the region in use, from its start to the end of the highest allocation
+    pub used_bytes: usize,
+    /// Freed or padding bytes below the highest allocation, available for reuse
+    pub free_bytes: usize,
+    pub total_allocations: u64,
+}
+
+/// Allocator for host data placed in guest memory, such as returned strings or argv and environ
+/// buffers, without calling into the guest's `malloc`. It manages `capacity` bytes from `start`,
+/// a region the guest must leave alone, and grows the memory as allocations reach past its end.
+///
+/// Allocations come from the lowest free block they fit in, or else from the end of the used part
+/// of the region. Address 0 is never handed out so allocations can't be mistaken for null.
+#[derive(Debug)]
+pub struct ArenaAllocator {
+    start: u32,
+    end: u32,
+    /// End of the highest allocation, everything from here to `end` is unused
+    top: u32,
+    /// Free blocks below `top`, start to end, never adjacent to one another
+    free: BTreeMap<u32, u32>,
+    /// Live allocations, start to size
+    allocations: BTreeMap<u32, u32>,
+    allocated: usize,
+    peak: usize,
+    total_allocations: u64,
+}
+
+impl ArenaAllocator {
+    pub fn new(start: u32, capacity: u32) -> Self {
+        let start = start.max(1);
+        Self {
+            start,
+            // Addresses are i32 throughout the memory api
+            end: start.saturating_add(capacity).min(i32::MAX as u32),
+            top: start,
+            free: BTreeMap::new(),
+            allocations: BTreeMap::new(),
+            allocated: 0,
+            peak: 0,
+            total_allocations: 0,
+        }
+    }
+
+    /// Allocates `size` bytes aligned to `align`, which must be a power of two. Fails with
+    /// `LimitExceeded` once the region is exhausted, or with the error of a failed grow. Zero
+    /// sized allocations take a byte so every allocation has a distinct address. See `reserve` for
+    /// what a grow capped by a resource limiter leaves behind.
+    pub fn alloc(
+        &mut self,
+        memory: &mut LinearMemory,
+        size: u32,
+        align: u32,
+    ) -> Result<u32, MemoryError> {
+        if !align.is_power_of_two() {
+            return Err(MemoryError::InvalidRange);
+        }
+        let size = size.max(1);
+        let address = match self.take_free(size, align) {
+            Some(address) => address,
+            None => self.bump(memory, size, align)?,
+        };
+        self.allocations.insert(address, size);
+        self.allocated += size as usize;
+        self.peak = self.peak.max(self.allocated);
+        self.total_allocations += 1;
+        Ok(address)
+    }
+
+    /// Frees the allocation at `address`, failing with `InvalidRange` if there is none.
+    pub fn free(&mut self, address: u32) -> Result<(), MemoryError> {
+        let size = self
+            .allocations
+            .remove(&address)
+            .ok_or(MemoryError::InvalidRange)?;
+        self.allocated -= size as usize;
+        self.release(address, address + size);
+        Ok(())
+    }
+
+    /// Resizes the allocation at `address` to `size` bytes, in place when it can, otherwise by
+    /// moving it to a new allocation aligned to `align` and copying its contents. Address 0
+    /// allocates like `alloc`. On failure the original allocation is left untouched.
+    pub fn realloc(
+        &mut self,
+        memory: &mut LinearMemory,
+        address: u32,
+        size: u32,
+        align: u32,
+    ) -> Result<u32, MemoryError> {
+        if address == 0 {
+            return self.alloc(memory, size, align);
+        }
+        let old_size = *self
+            .allocations
+            .get(&address)
+            .ok_or(MemoryError::InvalidRange)?;
+        if !align.is_power_of_two() {
+            return Err(MemoryError::InvalidRange);
+        }
+        let size = size.max(1);
+
+        if address.is_multiple_of(align) {
+            let resized = if size <= old_size {
+                self.release(address + size, address + old_size);
+                true
+            } else {
+                self.extend(memory, address + old_size, address + size)?
+            };
+            if resized {
+                self.allocations.insert(address, size);
+                self.allocated = self.allocated - old_size as usize + size as usize;
+                self.peak = self.peak.max(self.allocated);
+                return Ok(address);
+            }
+        }
+
+        let moved = self.alloc(memory, size, align)?;
+        memory.copy_within(address as i32, moved as i32, old_size as i32);
+        self.free(address)?;
+        Ok(moved)
+    }
+
+    /// Size of the live allocation at `address`.
+    pub fn allocation_size(&self, address: u32) -> Option<u32> {
+        self.allocations.get(&address).copied()
+    }
+
+    pub fn stats(&self) -> ArenaStats {
+        let used_bytes = (self.top - self.start) as usize;
+        ArenaStats {
+            allocated_bytes: self.allocated,
+            allocation_count: self.allocations.len(),
+            peak_allocated_bytes: self.peak,
+            used_bytes,
+            free_bytes: used_bytes - self.allocated,
+            total_allocations: self.total_allocations,
+        }
+    }
+
+    /// Carves an allocation out of the lowest free block with room for it.
+    fn take_free(&mut self, size: u32, align: u32) -> Option<u32> {
+        let (block_start, block_end, address) =
+            self.free.iter().find_map(|(&block_start, &block_end)| {
+                let address = block_start.checked_next_multiple_of(align)?;
+                (address.checked_add(size)? <= block_end).then_some((
+                    block_start,
+                    block_end,
+                    address,
+                ))
+            })?;
+        self.free.remove(&block_start);
+        if block_start < address {
+            self.free.insert(block_start, address);
+        }
+        if address + size < block_end {
+            self.free.insert(address + size, block_end);
+        }
+        Some(address)
+    }
+
+    /// Allocates past the highest allocation, growing the memory to cover it.
+    fn bump(
+        &mut self,
+        memory: &mut LinearMemory,
+        size: u32,
+        align: u32,
+    ) -> Result<u32, MemoryError> {
+        let address = self
+            .top
+            .checked_next_multiple_of(align)
+            .ok_or(MemoryError::LimitExceeded)?;
+        let end = address
+            .checked_add(size)
+            .filter(|&end| end <= self.end)
+            .ok_or(MemoryError::LimitExceeded)?;
+        reserve(memory, end)?;
+        let padding = self.top..address;
+        self.top = end;
+        if !padding.is_empty() {
+            self.release(padding.start, padding.end);
+        }
+        Ok(address)
+    }
+
+    /// Extends the allocation ending at `from` up to `to` without moving it, false if the bytes
+    /// after it are taken.
+    fn extend(
+        &mut self,
+        memory: &mut LinearMemory,
+        from: u32,
+        to: u32,
+    ) -> Result<bool, MemoryError> {
+        if from == self.top {
+            if to > self.end {
+                return Ok(false);
+            }
+            reserve(memory, to)?;
+            self.top = to;
+            return Ok(true);
+        }
+        match self.free.get(&from) {
+            Some(&block_end) if block_end >= to => {
+                self.free.remove(&from);
+                if to < block_end {
+                    self.free.insert(to, block_end);
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Returns `start..end` to the free blocks, merging it with its neighbours, or lowers the top
+    /// if it is the highest block.
+    fn release(&mut self, mut start: u32, mut end: u32) {
+        if start == end {
+            return;
+        }
+        if let Some((&previous_start, &previous_end)) = self.free.range(..start).next_back() {
+            if previous_end == start {
+                self.free.remove(&previous_start);
+                start = previous_start;
+            }
+        }
+        if let Some(next_end) = self.free.remove(&end) {
+            end = next_end;
+        }
+        if end == self.top {
+            self.top = start;
+        } else {
+            self.free.insert(start, end);
+        }
+    }
+}
+
+/// Grows `memory` until it is at least `end` bytes long. A resource limiter may grant fewer pages
+/// than that, the memory then keeps the pages it was granted, since there is no shrinking it
+/// back, and the allocation fails with `LimitExceeded`. Later allocations which fit in those pages
+/// use them without growing again.
+fn reserve(memory: &mut LinearMemory, end: u32) -> Result<(), MemoryError> {
+    let Some(missing) = (end as usize).checked_sub(memory.size()) else {
+        return Ok(());
+    };
+    let pages = missing.div_ceil(PAGE_SIZE as usize) as u32;
+    if memory.try_grow(pages)? < pages {
+        return Err(MemoryError::LimitExceeded);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::MemoryBudget;
+    use alloc::sync::Arc;
+
+    const PAGE: u32 = PAGE_SIZE;
+
+    #[test]
+    fn test_alloc_reuses_and_merges_free_blocks() {
+        let mut memory = LinearMemory::new(1);
+        let mut arena = ArenaAllocator::new(0, PAGE);
+
+        let first = arena.alloc(&mut memory, 10, 8).unwrap();
+        // Fits in the padding left before the first allocation
+        let second = arena.alloc(&mut memory, 4, 4).unwrap();
+        let third = arena.alloc(&mut memory, 16, 16).unwrap();
+        assert_eq!((first, second, third), (8, 4, 32));
+        assert_eq!(arena.stats().free_bytes, 3 + 14);
+
+        arena.free(first).unwrap();
+        arena.free(second).unwrap();
+        assert_eq!(arena.alloc(&mut memory, 20, 1).unwrap(), 1);
+        assert!(matches!(arena.free(second), Err(MemoryError::InvalidRange)));
+
+        // Freeing the highest allocation gives its bytes back to the unused end of the region
+        arena.free(third).unwrap();
+        assert_eq!(
+            arena.stats(),
+            ArenaStats {
+                allocated_bytes: 20,
+                allocation_count: 1,
+                peak_allocated_bytes: 36,
+                used_bytes: 20,
+                free_bytes: 0,
+                total_allocations: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn test_alloc_grows_memory() {
+        let mut memory = LinearMemory::new(1);
+        let mut arena = ArenaAllocator::new(PAGE, 2 * PAGE);
+
+        let address = arena.alloc(&mut memory, PAGE + 1, 8).unwrap();
+        assert_eq!(address, PAGE);
+        assert_eq!(memory.size(), 3 * PAGE as usize);
+        memory.write_i32_to_u8((address + PAGE) as i32, 1);
+
+        assert!(matches!(
+            arena.alloc(&mut memory, PAGE, 8),
+            Err(MemoryError::LimitExceeded)
+        ));
+        assert!(matches!(
+            arena.alloc(&mut memory, 1, 3),
+            Err(MemoryError::InvalidRange)
+        ));
+    }
+
+    #[test]
+    fn test_capped_grow_keeps_granted_pages() {
+        let mut memory = LinearMemory::new(1);
+        let budget = Arc::new(MemoryBudget::new(2 * PAGE as usize));
+        memory.set_limiter(budget).unwrap();
+        let mut arena = ArenaAllocator::new(PAGE, 3 * PAGE);
+
+        assert!(matches!(
+            arena.alloc(&mut memory, 2 * PAGE, 8),
+            Err(MemoryError::LimitExceeded)
+        ));
+        assert_eq!(memory.size(), 2 * PAGE as usize);
+        assert_eq!(arena.alloc(&mut memory, PAGE, 8).unwrap(), PAGE);
+        assert_eq!(arena.stats().used_bytes, PAGE as usize);
+    }
+
+    #[test]
+    fn test_realloc() {
+        let mut memory = LinearMemory::new(1);
+        let mut arena = ArenaAllocator::new(1024, PAGE);
+
+        let first = arena.alloc(&mut memory, 8, 8).unwrap();
+        memory.write_bytes(first as i32, b"argument");
+        // The highest allocation grows in place
+        assert_eq!(arena.realloc(&mut memory, first, 32, 8).unwrap(), first);
+
+        let second = arena.alloc(&mut memory, 8, 8).unwrap();
+        let moved = arena.realloc(&mut memory, first, 64, 8).unwrap();
+        assert_eq!(moved, second + 8);
+        assert_eq!(memory.read_bytes(moved as i32, 8), b"argument");
+        assert_eq!(arena.allocation_size(first), None);
+
+        // Shrinking frees the tail, which the next allocation picks up
+        assert_eq!(arena.realloc(&mut memory, moved, 16, 8).unwrap(), moved);
+        assert_eq!(arena.alloc(&mut memory, 4, 4).unwrap(), first);
+        assert_eq!(arena.stats().used_bytes, (moved + 16 - 1024) as usize);
+    }
+}
@@ -0,0 +1,402 @@
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut, Range};
+use core::{ptr, slice};
+#[cfg(feature = "mmap")]
+use memmap2::{MmapMut, MmapOptions};
+
+use crate::error::MemoryError;
+use crate::protect::Protection;
+
+#[cfg(feature = "std")]
+pub type BackingError = std::io::Error;
+
+/// Without `std` a backing can only fail to grow by running out of memory.
+#[cfg(not(feature = "std"))]
+#[derive(Debug)]
+pub struct BackingError;
+
+#[cfg(not(feature = "std"))]
+impl core::fmt::Display for BackingError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "out of memory")
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl core::error::Error for BackingError {}
+
+#[cfg(feature = "std")]
+pub(crate) fn out_of_memory() -> BackingError {
+    std::io::Error::from(std::io::ErrorKind::OutOfMemory)
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn out_of_memory() -> BackingError {
+    BackingError
+}
+
+/// Storage underneath a `LinearMemory`.
+///
+/// The base must be aligned to at least 8 bytes so naturally aligned wasm atomics are aligned on
+/// the host too, and every byte added by `grow` must read as zero.
+pub trait Backing: Send + Sync {
+    /// Resizes the storage to `new_len` bytes keeping its contents, the base may move.
+    fn grow(&mut self, new_len: usize) -> Result<(), BackingError>;
+
+    fn as_ptr(&self) -> *const u8;
+
+    fn as_mut_ptr(&mut self) -> *mut u8;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Zeroes every byte keeping the current length.
+    fn reset(&mut self) {
+        let len = self.len();
+        unsafe { ptr::write_bytes(self.as_mut_ptr(), 0, len) };
+    }
+
+    /// Applies `protection` to the page aligned byte `range` in hardware, so a forbidden direct
+    /// access through a descriptor raises a signal instead of going unnoticed. The default leaves
+    /// enforcement to the software checks.
+    fn protect(&mut self, range: Range<usize>, protection: Protection) -> Result<(), BackingError> {
+        let _ = (range, protection);
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+fn mprotect(
+    base: *mut u8,
+    range: Range<usize>,
+    protection: Protection,
+) -> Result<(), BackingError> {
+    let flags = match protection {
+        Protection::NoAccess => libc::PROT_NONE,
+        Protection::ReadOnly => libc::PROT_READ,
+        Protection::ReadWrite => libc::PROT_READ | libc::PROT_WRITE,
+    };
+    let result = unsafe { libc::mprotect(base.add(range.start).cast(), range.len(), flags) };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+impl Deref for dyn Backing {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len()) }
+    }
+}
+
+impl DerefMut for dyn Backing {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        let len = self.len();
+        unsafe { slice::from_raw_parts_mut(self.as_mut_ptr(), len) }
+    }
+}
+
+/// Anonymous private mapping, the default backing.
+#[cfg(feature = "mmap")]
+pub struct MmapBacking(MmapMut);
+
+#[cfg(feature = "mmap")]
+impl MmapBacking {
+    pub fn new(len: usize) -> Result<Self, BackingError> {
+        MmapOptions::new().len(len).map_anon().map(Self)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Backing for MmapBacking {
+    #[cfg(target_os = "linux")]
+    fn grow(&mut self, new_len: usize) -> Result<(), BackingError> {
+        unsafe {
+            self.0
+                .remap(new_len, memmap2::RemapOptions::new().may_move(true))
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn grow(&mut self, new_len: usize) -> Result<(), BackingError> {
+        let current_len = self.0.len();
+
+        let mut new_map = MmapOptions::new().len(new_len).map_anon()?;
+
+        new_map[..current_len].copy_from_slice(&self.0[..current_len]);
+        self.0 = new_map;
+        Ok(())
+    }
+
+    fn as_ptr(&self) -> *const u8 {
+        self.0.as_ptr()
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.0.as_mut_ptr()
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[cfg(unix)]
+    fn protect(&mut self, range: Range<usize>, protection: Protection) -> Result<(), BackingError> {
+        mprotect(self.0.as_mut_ptr(), range, protection)
+    }
+}
+
+/// Heap allocated buffer for platforms without mmap, and for tests.
+#[derive(Default)]
+pub struct HeapBacking {
+    // Stored as words so the base meets the alignment of 64 bit atomics
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl HeapBacking {
+    pub fn new(len: usize) -> Self {
+        let mut backing = Self::default();
+        backing.resize(len);
+        backing
+    }
+
+    fn resize(&mut self, len: usize) {
+        self.words.resize(len.div_ceil(size_of::<u64>()), 0);
+        self.len = len;
+    }
+}
+
+impl Backing for HeapBacking {
+    fn grow(&mut self, new_len: usize) -> Result<(), BackingError> {
+        let additional = new_len
+            .div_ceil(size_of::<u64>())
+            .saturating_sub(self.words.len());
+        self.words
+            .try_reserve_exact(additional)
+            .map_err(|_| out_of_memory())?;
+        self.resize(new_len);
+        Ok(())
+    }
+
+    fn as_ptr(&self) -> *const u8 {
+        self.words.as_ptr().cast()
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.words.as_mut_ptr().cast()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn reset(&mut self) {
+        self.words.fill(0);
+    }
+}
+
+/// Shared mapping of an anonymous memfd, so the memory can be mapped by other processes or
+/// handed to the kernel by file descriptor.
+#[cfg(all(feature = "mmap", target_os = "linux"))]
+pub struct MemfdBacking {
+    file: std::fs::File,
+    map: MmapMut,
+}
+
+#[cfg(all(feature = "mmap", target_os = "linux"))]
+impl MemfdBacking {
+    pub fn new(len: usize) -> Result<Self, BackingError> {
+        use std::os::fd::FromRawFd;
+
+        let fd = unsafe { libc::memfd_create(c"linmem".as_ptr(), libc::MFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let file = unsafe { std::fs::File::from_raw_fd(fd) };
+        file.set_len(len as u64)?;
+        let map = unsafe { MmapOptions::new().len(len).map_mut(&file)? };
+
+        Ok(Self { file, map })
+    }
+
+    pub fn file(&self) -> &std::fs::File {
+        &self.file
+    }
+}
+
+#[cfg(all(feature = "mmap", target_os = "linux"))]
+impl Backing for MemfdBacking {
+    fn grow(&mut self, new_len: usize) -> Result<(), BackingError> {
+        self.file.set_len(new_len as u64)?;
+        unsafe {
+            self.map
+                .remap(new_len, memmap2::RemapOptions::new().may_move(true))
+        }
+    }
+
+    fn as_ptr(&self) -> *const u8 {
+        self.map.as_ptr()
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.map.as_mut_ptr()
+    }
+
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    fn protect(&mut self, range: Range<usize>, protection: Protection) -> Result<(), BackingError> {
+        mprotect(self.map.as_mut_ptr(), range, protection)
+    }
+}
+
+/// Memory owned by the embedder. It never moves, so growing only succeeds within `capacity`.
+pub struct ExternalBacking {
+    base: *mut u8,
+    len: usize,
+    capacity: usize,
+}
+
+unsafe impl Send for ExternalBacking {}
+unsafe impl Sync for ExternalBacking {}
+
+impl ExternalBacking {
+    /// Fails with `MemoryError::InvalidArgument` if `base` is null or not aligned to 8 bytes, or
+    /// `len` exceeds `capacity`. Atomics and `WasmSlice` rely on the alignment for soundness.
+    ///
+    /// # Safety
+    ///
+    /// `base` must be valid for reads and writes of `capacity` bytes and not accessed other than
+    /// through the memory until it is dropped. The first `len` bytes become the initial contents.
+    pub unsafe fn new(base: *mut u8, len: usize, capacity: usize) -> Result<Self, MemoryError> {
+        if base.is_null() || !base.cast::<u64>().is_aligned() || len > capacity {
+            return Err(MemoryError::InvalidArgument);
+        }
+        Ok(Self {
+            base,
+            len,
+            capacity,
+        })
+    }
+}
+
+impl Backing for ExternalBacking {
+    fn grow(&mut self, new_len: usize) -> Result<(), BackingError> {
+        if new_len > self.capacity {
+            return Err(out_of_memory());
+        }
+        if new_len > self.len {
+            unsafe { ptr::write_bytes(self.base.add(self.len), 0, new_len - self.len) };
+        }
+        self.len = new_len;
+        Ok(())
+    }
+
+    fn as_ptr(&self) -> *const u8 {
+        self.base
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.base
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::LinearMemory;
+
+    const PAGE_SIZE: usize = 64 * 1024;
+
+    #[test]
+    fn test_heap_backing() {
+        let mut memory = LinearMemory::with_backing(HeapBacking::new(PAGE_SIZE));
+        memory.write_i64(8, -1);
+        memory.atomic_rmw_add_i64(16, 5);
+
+        assert!(memory.grow(1));
+        assert_eq!(memory.size(), 2 * PAGE_SIZE);
+        assert_eq!(memory.read_i64(8), -1);
+        assert_eq!(memory.atomic_read_i64(16), 5);
+        assert_eq!(memory.read_i64(PAGE_SIZE as i32), 0);
+    }
+
+    #[test]
+    fn test_external_backing() {
+        let mut buffer = vec![u64::MAX; 3 * PAGE_SIZE / 8];
+        let backing =
+            unsafe { ExternalBacking::new(buffer.as_mut_ptr().cast(), PAGE_SIZE, 2 * PAGE_SIZE) }
+                .unwrap();
+        let mut memory = LinearMemory::with_backing(backing);
+
+        assert_eq!(memory.read_i32(0), -1);
+        assert!(memory.grow(1));
+        assert_eq!(memory.read_i32(PAGE_SIZE as i32), 0);
+        assert!(!memory.grow(1));
+
+        memory.reset().unwrap();
+        assert_eq!(memory.read_i32(0), 0);
+        drop(memory);
+        assert_eq!(buffer[2 * PAGE_SIZE / 8], u64::MAX);
+    }
+
+    #[test]
+    fn test_external_backing_rejects_invalid_buffers() {
+        let mut buffer = vec![0u64; 2];
+        let base = buffer.as_mut_ptr().cast::<u8>();
+
+        for (base, len, capacity) in [
+            (unsafe { base.add(4) }, 0, 8),
+            (base, 16, 8),
+            (ptr::null_mut(), 0, 0),
+        ] {
+            assert!(matches!(
+                unsafe { ExternalBacking::new(base, len, capacity) },
+                Err(MemoryError::InvalidArgument)
+            ));
+        }
+    }
+
+    #[cfg(all(feature = "mmap", target_os = "linux"))]
+    #[test]
+    fn test_memfd_backing() {
+        use std::io::Read;
+
+        let backing = MemfdBacking::new(PAGE_SIZE).unwrap();
+        let mut file = backing.file().try_clone().unwrap();
+        let mut memory = LinearMemory::with_backing(backing);
+
+        memory.write_bytes(0, b"shared");
+        assert!(memory.grow(1));
+        memory.write_bytes(PAGE_SIZE as i32, b"grown");
+
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents.len(), 2 * PAGE_SIZE);
+        assert_eq!(&contents[..6], b"shared");
+        assert_eq!(&contents[PAGE_SIZE..PAGE_SIZE + 5], b"grown");
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_reset() {
+        let mut backing = MmapBacking::new(PAGE_SIZE).unwrap();
+        unsafe { backing.as_mut_ptr().write(1) };
+
+        backing.reset();
+
+        assert_eq!(unsafe { backing.as_ptr().read() }, 0);
+        assert_eq!(backing.len(), PAGE_SIZE);
+    }
+}
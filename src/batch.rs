@@ -0,0 +1,493 @@
+use core::fmt;
+
+use crate::memory::{AccessKind, LinearMemory};
+use crate::trap::{self, Trap};
+
+/// One instruction of a batch, 24 bytes with C layout.
+///
+/// | opcode                   | address     | operand      | operand2          |
+/// |--------------------------|-------------|--------------|-------------------|
+/// | `Read*`, `AtomicRead*`   | address     | unused       | unused            |
+/// | `Write*`, `AtomicWrite*` | address     | value        | unused            |
+/// | `AtomicRmw*`             | address     | value        | unused            |
+/// | `AtomicCompareExchange*` | address     | expected     | replacement       |
+/// | `MemoryFill`             | destination | byte value   | byte count        |
+/// | `MemoryCopy`             | source      | destination  | byte count        |
+/// | `AtomicFence`            | unused      | unused       | unused            |
+/// | `AtomicNotify`           | address     | waiter count | unused            |
+///
+/// Values and results are carried in an `i64`: integers are sign or zero extended exactly as the
+/// matching `LinearMemory` function returns them, floats travel as their IEEE 754 bits. A byte
+/// count or copy destination outside `0..=i32::MAX` traps as out of bounds.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BatchOp {
+    pub opcode: u32,
+    pub address: i32,
+    pub operand: i64,
+    pub operand2: i64,
+}
+
+impl BatchOp {
+    /// Byte count of `MemoryFill` and `MemoryCopy`, out of bounds unless it fits the `i32` the
+    /// bulk memory functions take.
+    fn byte_count(&self) -> Result<i32, Trap> {
+        i32::try_from(self.operand2)
+            .ok()
+            .filter(|&byte_count| byte_count >= 0)
+            .ok_or(Trap::OutOfBounds)
+    }
+
+    fn copy_destination(&self) -> Result<i32, Trap> {
+        i32::try_from(self.operand).map_err(|_| Trap::OutOfBounds)
+    }
+}
+
+/// The first trapping instruction of a batch, every instruction before it has run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchTrap {
+    pub index: usize,
+    pub trap: Trap,
+}
+
+impl fmt::Display for BatchTrap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "instruction {} trapped: {}", self.index, self.trap)
+    }
+}
+
+impl core::error::Error for BatchTrap {}
+
+macro_rules! batch_opcodes {
+    ($($opcode:ident = $value:literal => $kind:ident $function:ident $width:literal,)*) => {
+        /// Opcodes understood by `execute_batch`, each named after the `LinearMemory` function it
+        /// runs. The discriminants are part of the C ABI and never change.
+        #[repr(u32)]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum BatchOpcode {
+            $($opcode = $value,)*
+            MemoryFill = 86,
+            MemoryCopy = 87,
+            AtomicFence = 88,
+            AtomicNotify = 89,
+        }
+
+        impl BatchOpcode {
+            pub fn from_u32(value: u32) -> Option<Self> {
+                match value {
+                    $($value => Some(BatchOpcode::$opcode),)*
+                    86 => Some(BatchOpcode::MemoryFill),
+                    87 => Some(BatchOpcode::MemoryCopy),
+                    88 => Some(BatchOpcode::AtomicFence),
+                    89 => Some(BatchOpcode::AtomicNotify),
+                    _ => None,
+                }
+            }
+        }
+
+        impl LinearMemory {
+            fn check_op(&self, opcode: BatchOpcode, op: &BatchOp) -> Result<(), Trap> {
+                match opcode {
+                    $(BatchOpcode::$opcode => {
+                        self.check_access(
+                            op.address,
+                            $width,
+                            Some(batch_opcodes!(@access $kind)),
+                            batch_opcodes!(@atomic $kind),
+                        )
+                    })*
+                    BatchOpcode::MemoryFill => {
+                        let byte_count = op.byte_count()? as usize;
+                        self.check_access(op.address, byte_count, Some(AccessKind::Write), false)
+                    }
+                    BatchOpcode::MemoryCopy => {
+                        let byte_count = op.byte_count()? as usize;
+                        self.check_access(op.address, byte_count, Some(AccessKind::Read), false)?;
+                        self.check_access(
+                            op.copy_destination()?,
+                            byte_count,
+                            Some(AccessKind::Write),
+                            false,
+                        )
+                    }
+                    BatchOpcode::AtomicFence => Ok(()),
+                    BatchOpcode::AtomicNotify => self.check_access(op.address, 4, None, true),
+                }
+            }
+
+            fn run_op(&mut self, opcode: BatchOpcode, op: &BatchOp) -> i64 {
+                match opcode {
+                    $(BatchOpcode::$opcode => batch_opcodes!(@run self, op, $kind, $function),)*
+                    BatchOpcode::MemoryFill => {
+                        let byte_count = op.byte_count().expect("Checked by check_op");
+                        self.fill(op.address, byte_count, op.operand as u8);
+                        0
+                    }
+                    BatchOpcode::MemoryCopy => {
+                        let byte_count = op.byte_count().expect("Checked by check_op");
+                        let destination = op.copy_destination().expect("Checked by check_op");
+                        self.copy_within(op.address, destination, byte_count);
+                        0
+                    }
+                    BatchOpcode::AtomicFence => {
+                        self.atomic_fence();
+                        0
+                    }
+                    BatchOpcode::AtomicNotify => self.notify(op.address, op.operand as i32) as i64,
+                }
+            }
+        }
+    };
+
+    (@access load) => { AccessKind::Read };
+    (@access load_f32) => { AccessKind::Read };
+    (@access load_f64) => { AccessKind::Read };
+    (@access atomic_load) => { AccessKind::Read };
+    (@access rmw_i32) => { AccessKind::ReadWrite };
+    (@access rmw_i64) => { AccessKind::ReadWrite };
+    (@access cmpxchg_i32) => { AccessKind::ReadWrite };
+    (@access cmpxchg_i64) => { AccessKind::ReadWrite };
+    (@access $kind:ident) => { AccessKind::Write };
+
+    (@atomic atomic_load) => { true };
+    (@atomic atomic_store_i32) => { true };
+    (@atomic atomic_store_i64) => { true };
+    (@atomic rmw_i32) => { true };
+    (@atomic rmw_i64) => { true };
+    (@atomic cmpxchg_i32) => { true };
+    (@atomic cmpxchg_i64) => { true };
+    (@atomic $kind:ident) => { false };
+
+    (@run $memory:ident, $op:ident, load, $function:ident) => {
+        $memory.$function($op.address) as i64
+    };
+    (@run $memory:ident, $op:ident, atomic_load, $function:ident) => {
+        $memory.$function($op.address) as i64
+    };
+    (@run $memory:ident, $op:ident, load_f32, $function:ident) => {
+        $memory.$function($op.address).to_bits() as i64
+    };
+    (@run $memory:ident, $op:ident, load_f64, $function:ident) => {
+        $memory.$function($op.address).to_bits() as i64
+    };
+    (@run $memory:ident, $op:ident, store_i32, $function:ident) => {{
+        $memory.$function($op.address, $op.operand as i32);
+        0
+    }};
+    (@run $memory:ident, $op:ident, atomic_store_i32, $function:ident) => {{
+        $memory.$function($op.address, $op.operand as i32);
+        0
+    }};
+    (@run $memory:ident, $op:ident, store_i64, $function:ident) => {{
+        $memory.$function($op.address, $op.operand);
+        0
+    }};
+    (@run $memory:ident, $op:ident, atomic_store_i64, $function:ident) => {{
+        $memory.$function($op.address, $op.operand);
+        0
+    }};
+    (@run $memory:ident, $op:ident, store_f32, $function:ident) => {{
+        $memory.$function($op.address, f32::from_bits($op.operand as u32));
+        0
+    }};
+    (@run $memory:ident, $op:ident, store_f64, $function:ident) => {{
+        $memory.$function($op.address, f64::from_bits($op.operand as u64));
+        0
+    }};
+    (@run $memory:ident, $op:ident, rmw_i32, $function:ident) => {
+        $memory.$function($op.address, $op.operand as i32) as i64
+    };
+    (@run $memory:ident, $op:ident, rmw_i64, $function:ident) => {
+        $memory.$function($op.address, $op.operand)
+    };
+    (@run $memory:ident, $op:ident, cmpxchg_i32, $function:ident) => {
+        $memory.$function($op.address, $op.operand as i32, $op.operand2 as i32) as i64
+    };
+    (@run $memory:ident, $op:ident, cmpxchg_i64, $function:ident) => {
+        $memory.$function($op.address, $op.operand, $op.operand2)
+    };
+}
+
+batch_opcodes! {
+    ReadI32 = 0 => load read_i32 4,
+    ReadI32FromI8 = 1 => load read_i32_from_i8 1,
+    ReadI32FromU8 = 2 => load read_i32_from_u8 1,
+    ReadI32FromI16 = 3 => load read_i32_from_i16 2,
+    ReadI32FromU16 = 4 => load read_i32_from_u16 2,
+    ReadI64 = 5 => load read_i64 8,
+    ReadI64FromI8 = 6 => load read_i64_from_i8 1,
+    ReadI64FromU8 = 7 => load read_i64_from_u8 1,
+    ReadI64FromI16 = 8 => load read_i64_from_i16 2,
+    ReadI64FromU16 = 9 => load read_i64_from_u16 2,
+    ReadI64FromI32 = 10 => load read_i64_from_i32 4,
+    ReadI64FromU32 = 11 => load read_i64_from_u32 4,
+    ReadF32 = 12 => load_f32 read_f32 4,
+    ReadF64 = 13 => load_f64 read_f64 8,
+    WriteI32 = 14 => store_i32 write_i32 4,
+    WriteI32ToI8 = 15 => store_i32 write_i32_to_i8 1,
+    WriteI32ToI16 = 16 => store_i32 write_i32_to_i16 2,
+    WriteI64 = 17 => store_i64 write_i64 8,
+    WriteI64ToI8 = 18 => store_i64 write_i64_to_i8 1,
+    WriteI64ToI16 = 19 => store_i64 write_i64_to_i16 2,
+    WriteI64ToI32 = 20 => store_i64 write_i64_to_i32 4,
+    WriteF32 = 21 => store_f32 write_f32 4,
+    WriteF64 = 22 => store_f64 write_f64 8,
+    AtomicReadI32 = 23 => atomic_load atomic_read_i32 4,
+    AtomicReadI32FromU8 = 24 => atomic_load atomic_read_i32_from_u8 1,
+    AtomicReadI32FromU16 = 25 => atomic_load atomic_read_i32_from_u16 2,
+    AtomicReadI64 = 26 => atomic_load atomic_read_i64 8,
+    AtomicReadI64FromU8 = 27 => atomic_load atomic_read_i64_from_u8 1,
+    AtomicReadI64FromU16 = 28 => atomic_load atomic_read_i64_from_u16 2,
+    AtomicReadI64FromU32 = 29 => atomic_load atomic_read_i64_from_u32 4,
+    AtomicWriteI32 = 30 => atomic_store_i32 atomic_write_i32 4,
+    AtomicWriteI32ToU8 = 31 => atomic_store_i32 atomic_write_i32_to_u8 1,
+    AtomicWriteI32ToU16 = 32 => atomic_store_i32 atomic_write_i32_to_u16 2,
+    AtomicWriteI64 = 33 => atomic_store_i64 atomic_write_i64 8,
+    AtomicWriteI64ToU8 = 34 => atomic_store_i64 atomic_write_i64_to_u8 1,
+    AtomicWriteI64ToU16 = 35 => atomic_store_i64 atomic_write_i64_to_u16 2,
+    AtomicWriteI64ToU32 = 36 => atomic_store_i64 atomic_write_i64_to_u32 4,
+    AtomicRmwAddI32 = 37 => rmw_i32 atomic_rmw_add_i32 4,
+    AtomicRmwSubI32 = 38 => rmw_i32 atomic_rmw_sub_i32 4,
+    AtomicRmwAndI32 = 39 => rmw_i32 atomic_rmw_and_i32 4,
+    AtomicRmwOrI32 = 40 => rmw_i32 atomic_rmw_or_i32 4,
+    AtomicRmwXorI32 = 41 => rmw_i32 atomic_rmw_xor_i32 4,
+    AtomicRmwExchangeI32 = 42 => rmw_i32 atomic_rmw_exchange_i32 4,
+    AtomicRmwAddI32ToI8 = 43 => rmw_i32 atomic_rmw_add_i32_to_i8 1,
+    AtomicRmwSubI32ToI8 = 44 => rmw_i32 atomic_rmw_sub_i32_to_i8 1,
+    AtomicRmwAndI32ToI8 = 45 => rmw_i32 atomic_rmw_and_i32_to_i8 1,
+    AtomicRmwOrI32ToI8 = 46 => rmw_i32 atomic_rmw_or_i32_to_i8 1,
+    AtomicRmwXorI32ToI8 = 47 => rmw_i32 atomic_rmw_xor_i32_to_i8 1,
+    AtomicRmwExchangeI32ToI8 = 48 => rmw_i32 atomic_rmw_exchange_i32_to_i8 1,
+    AtomicRmwAddI32ToI16 = 49 => rmw_i32 atomic_rmw_add_i32_to_i16 2,
+    AtomicRmwSubI32ToI16 = 50 => rmw_i32 atomic_rmw_sub_i32_to_i16 2,
+    AtomicRmwAndI32ToI16 = 51 => rmw_i32 atomic_rmw_and_i32_to_i16 2,
+    AtomicRmwOrI32ToI16 = 52 => rmw_i32 atomic_rmw_or_i32_to_i16 2,
+    AtomicRmwXorI32ToI16 = 53 => rmw_i32 atomic_rmw_xor_i32_to_i16 2,
+    AtomicRmwExchangeI32ToI16 = 54 => rmw_i32 atomic_rmw_exchange_i32_to_i16 2,
+    AtomicRmwAddI64 = 55 => rmw_i64 atomic_rmw_add_i64 8,
+    AtomicRmwSubI64 = 56 => rmw_i64 atomic_rmw_sub_i64 8,
+    AtomicRmwAndI64 = 57 => rmw_i64 atomic_rmw_and_i64 8,
+    AtomicRmwOrI64 = 58 => rmw_i64 atomic_rmw_or_i64 8,
+    AtomicRmwXorI64 = 59 => rmw_i64 atomic_rmw_xor_i64 8,
+    AtomicRmwExchangeI64 = 60 => rmw_i64 atomic_rmw_exchange_i64 8,
+    AtomicRmwAddI64ToI8 = 61 => rmw_i64 atomic_rmw_add_i64_to_i8 1,
+    AtomicRmwSubI64ToI8 = 62 => rmw_i64 atomic_rmw_sub_i64_to_i8 1,
+    AtomicRmwAndI64ToI8 = 63 => rmw_i64 atomic_rmw_and_i64_to_i8 1,
+    AtomicRmwOrI64ToI8 = 64 => rmw_i64 atomic_rmw_or_i64_to_i8 1,
+    AtomicRmwXorI64ToI8 = 65 => rmw_i64 atomic_rmw_xor_i64_to_i8 1,
+    AtomicRmwExchangeI64ToI8 = 66 => rmw_i64 atomic_rmw_exchange_i64_to_i8 1,
+    AtomicRmwAddI64ToI16 = 67 => rmw_i64 atomic_rmw_add_i64_to_i16 2,
+    AtomicRmwSubI64ToI16 = 68 => rmw_i64 atomic_rmw_sub_i64_to_i16 2,
+    AtomicRmwAndI64ToI16 = 69 => rmw_i64 atomic_rmw_and_i64_to_i16 2,
+    AtomicRmwOrI64ToI16 = 70 => rmw_i64 atomic_rmw_or_i64_to_i16 2,
+    AtomicRmwXorI64ToI16 = 71 => rmw_i64 atomic_rmw_xor_i64_to_i16 2,
+    AtomicRmwExchangeI64ToI16 = 72 => rmw_i64 atomic_rmw_exchange_i64_to_i16 2,
+    AtomicRmwAddI64ToI32 = 73 => rmw_i64 atomic_rmw_add_i64_to_i32 4,
+    AtomicRmwSubI64ToI32 = 74 => rmw_i64 atomic_rmw_sub_i64_to_i32 4,
+    AtomicRmwAndI64ToI32 = 75 => rmw_i64 atomic_rmw_and_i64_to_i32 4,
+    AtomicRmwOrI64ToI32 = 76 => rmw_i64 atomic_rmw_or_i64_to_i32 4,
+    AtomicRmwXorI64ToI32 = 77 => rmw_i64 atomic_rmw_xor_i64_to_i32 4,
+    AtomicRmwExchangeI64ToI32 = 78 => rmw_i64 atomic_rmw_exchange_i64_to_i32 4,
+    AtomicCompareExchangeI32 = 79 => cmpxchg_i32 atomic_compare_exchange_i32 4,
+    AtomicCompareExchangeI32ToI8 = 80 => cmpxchg_i32 atomic_compare_exchange_i32_to_i8 1,
+    AtomicCompareExchangeI32ToI16 = 81 => cmpxchg_i32 atomic_compare_exchange_i32_to_i16 2,
+    AtomicCompareExchangeI64 = 82 => cmpxchg_i64 atomic_compare_exchange_i64 8,
+    AtomicCompareExchangeI64ToI8 = 83 => cmpxchg_i64 atomic_compare_exchange_i64_to_i8 1,
+    AtomicCompareExchangeI64ToI16 = 84 => cmpxchg_i64 atomic_compare_exchange_i64_to_i16 2,
+    AtomicCompareExchangeI64ToI32 = 85 => cmpxchg_i64 atomic_compare_exchange_i64_to_i32 4,
+}
+
+impl LinearMemory {
+    /// Runs `ops` in order writing one result per instruction, stores write 0. Execution stops at
+    /// the first instruction that would trap, leaving it and the remaining results untouched.
+    /// Traps with `Trap::OutOfBounds` before running anything if `results` is shorter than `ops`.
+    pub fn execute_batch(&mut self, ops: &[BatchOp], results: &mut [i64]) -> Result<(), BatchTrap> {
+        if results.len() < ops.len() {
+            trap::raise(Trap::OutOfBounds);
+        }
+
+        for (index, (op, result)) in ops.iter().zip(results.iter_mut()).enumerate() {
+            let opcode = BatchOpcode::from_u32(op.opcode)
+                .ok_or(Trap::InvalidOpcode)
+                .and_then(|opcode| self.check_op(opcode, op).map(|_| opcode))
+                .map_err(|trap| BatchTrap { index, trap })?;
+            *result = self.run_op(opcode, op);
+        }
+        Ok(())
+    }
+
+    /// `kind` is None for instructions which check bounds without touching memory.
+    fn check_access(
+        &self,
+        address: i32,
+        byte_count: usize,
+        kind: Option<AccessKind>,
+        atomic: bool,
+    ) -> Result<(), Trap> {
+        if atomic {
+            self.atomic_start(address, byte_count)?;
+        }
+        let range = self
+            .checked_range(address, byte_count)
+            .ok_or(Trap::OutOfBounds)?;
+        match kind {
+            Some(kind) if !self.is_accessible(range, kind) => Err(Trap::Protected),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(opcode: BatchOpcode, address: i32, operand: i64, operand2: i64) -> BatchOp {
+        BatchOp {
+            opcode: opcode as u32,
+            address,
+            operand,
+            operand2,
+        }
+    }
+
+    #[test]
+    fn test_execute_batch() {
+        let mut memory = LinearMemory::new(1);
+        let ops = [
+            op(BatchOpcode::WriteI32, 0, -5, 0),
+            op(BatchOpcode::ReadI32, 0, 0, 0),
+            op(BatchOpcode::ReadI64FromU32, 0, 0, 0),
+            op(BatchOpcode::WriteF64, 8, 2.5f64.to_bits() as i64, 0),
+            op(BatchOpcode::ReadF64, 8, 0, 0),
+            op(BatchOpcode::AtomicRmwAddI32, 16, 3, 0),
+            op(BatchOpcode::AtomicCompareExchangeI32, 16, 3, 9),
+            op(BatchOpcode::MemoryFill, 32, 0xAB, 4),
+            op(BatchOpcode::MemoryCopy, 32, 40, 4),
+            op(BatchOpcode::ReadI32FromU8, 43, 0, 0),
+            op(BatchOpcode::AtomicNotify, 16, 1, 0),
+        ];
+        let mut results = [-1; 11];
+
+        assert_eq!(memory.execute_batch(&ops, &mut results), Ok(()));
+
+        assert_eq!(
+            results,
+            [
+                0,
+                -5,
+                0xFFFF_FFFB,
+                0,
+                2.5f64.to_bits() as i64,
+                0,
+                3,
+                0,
+                0,
+                0xAB,
+                0
+            ]
+        );
+        assert_eq!(memory.read_i32(16), 9);
+    }
+
+    #[test]
+    fn test_execute_batch_stops_at_trap() {
+        let mut memory = LinearMemory::new(1);
+        let size = memory.size() as i32;
+        let ops = [
+            op(BatchOpcode::WriteI32, 0, 1, 0),
+            op(BatchOpcode::ReadI64, size - 4, 0, 0),
+            op(BatchOpcode::WriteI32, 0, 2, 0),
+        ];
+        let mut results = [-1; 3];
+
+        assert_eq!(
+            memory.execute_batch(&ops, &mut results),
+            Err(BatchTrap {
+                index: 1,
+                trap: Trap::OutOfBounds
+            })
+        );
+        assert_eq!(results, [0, -1, -1]);
+        assert_eq!(memory.read_i32(0), 1);
+    }
+
+    #[test]
+    fn test_execute_batch_bulk_byte_count_out_of_range() {
+        let mut memory = LinearMemory::new(1);
+        let mut results = [-1];
+
+        for (opcode, operand, operand2) in [
+            (BatchOpcode::MemoryFill, 0xAB, i32::MAX as i64 + 1),
+            (BatchOpcode::MemoryFill, 0xAB, (1 << 32) | 4),
+            (BatchOpcode::MemoryCopy, 8, -1),
+            (BatchOpcode::MemoryCopy, (1 << 32) | 8, 4),
+        ] {
+            assert_eq!(
+                memory.execute_batch(&[op(opcode, 0, operand, operand2)], &mut results),
+                Err(BatchTrap {
+                    index: 0,
+                    trap: Trap::OutOfBounds
+                })
+            );
+        }
+        assert_eq!(results, [-1]);
+        assert_eq!(memory.read_i32(0), 0);
+    }
+
+    #[test]
+    fn test_execute_batch_unaligned_atomic() {
+        let mut memory = LinearMemory::new(1);
+        let ops = [
+            op(BatchOpcode::WriteI64, 2, 1, 0),
+            op(BatchOpcode::AtomicReadI64, 2, 0, 0),
+        ];
+        let mut results = [0; 2];
+
+        assert_eq!(
+            memory.execute_batch(&ops, &mut results),
+            Err(BatchTrap {
+                index: 1,
+                trap: Trap::UnalignedAtomic
+            })
+        );
+    }
+
+    #[test]
+    fn test_execute_batch_short_results_trap() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let mut memory = LinearMemory::new(1);
+        let ops = [
+            op(BatchOpcode::WriteI32, 0, 1, 0),
+            op(BatchOpcode::WriteI32, 4, 2, 0),
+        ];
+        let mut results = [-1];
+
+        let trapped = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _ = memory.execute_batch(&ops, &mut results);
+        }));
+        assert_eq!(
+            *trapped.unwrap_err().downcast::<Trap>().unwrap(),
+            Trap::OutOfBounds
+        );
+        assert_eq!(results, [-1]);
+        assert_eq!(memory.read_i32(0), 0);
+    }
+
+    #[test]
+    fn test_execute_batch_invalid_opcode() {
+        let mut memory = LinearMemory::new(1);
+        let ops = [BatchOp {
+            opcode: 1000,
+            ..Default::default()
+        }];
+
+        assert_eq!(
+            memory.execute_batch(&ops, &mut [0]),
+            Err(BatchTrap {
+                index: 0,
+                trap: Trap::InvalidOpcode
+            })
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use alloc::string::String;
+use core::fmt;
+
+use crate::backing::BackingError;
+use crate::ptr::PtrError;
+use crate::trap::Trap;
+
+/// Error codes reported to C hosts through `linmem_last_error`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    None = 0,
+    SizeOverflow = 1,
+    MapFailed = 2,
+    Panic = 3,
+    InvalidRange = 4,
+    Trapped = 5,
+    LimitExceeded = 6,
+    Io = 7,
+    InvalidArgument = 8,
+}
+
+#[derive(Debug)]
+pub enum MemoryError {
+    /// The requested size does not fit in the address space
+    SizeOverflow,
+    /// The operating system could not map or remap the memory
+    MapFailed(BackingError),
+    /// An FFI export panicked, carrying the panic message
+    Panicked(String),
+    /// The requested range lies outside the memory
+    InvalidRange,
+    /// An FFI export trapped, for instance by touching a protected page
+    Trapped(Trap),
+    /// The memory's resource limiter refused to let it grow
+    LimitExceeded,
+    /// A host file descriptor transfer failed
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// An argument is invalid, such as an integer naming no variant of its enum or a misaligned
+    /// host buffer
+    InvalidArgument,
+}
+
+impl MemoryError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            MemoryError::SizeOverflow => ErrorCode::SizeOverflow,
+            MemoryError::MapFailed(_) => ErrorCode::MapFailed,
+            MemoryError::Panicked(_) => ErrorCode::Panic,
+            MemoryError::InvalidRange => ErrorCode::InvalidRange,
+            MemoryError::Trapped(_) => ErrorCode::Trapped,
+            MemoryError::LimitExceeded => ErrorCode::LimitExceeded,
+            #[cfg(feature = "std")]
+            MemoryError::Io(_) => ErrorCode::Io,
+            MemoryError::InvalidArgument => ErrorCode::InvalidArgument,
+        }
+    }
+}
+
+impl fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryError::SizeOverflow => write!(f, "memory size overflows the address space"),
+            MemoryError::MapFailed(error) => write!(f, "failed to map memory: {error}"),
+            MemoryError::Panicked(message) => write!(f, "panicked: {message}"),
+            MemoryError::InvalidRange => write!(f, "range exceeds the memory bounds"),
+            MemoryError::Trapped(trap) => write!(f, "trapped: {trap}"),
+            MemoryError::LimitExceeded => write!(f, "memory limit exceeded"),
+            #[cfg(feature = "std")]
+            MemoryError::Io(error) => write!(f, "i/o failed: {error}"),
+            MemoryError::InvalidArgument => write!(f, "invalid argument"),
+        }
+    }
+}
+
+impl core::error::Error for MemoryError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            MemoryError::MapFailed(error) => Some(error),
+            MemoryError::Trapped(trap) => Some(trap),
+            #[cfg(feature = "std")]
+            MemoryError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<PtrError> for MemoryError {
+    fn from(_: PtrError) -> Self {
+        MemoryError::InvalidRange
+    }
+}
+
+impl From<BackingError> for MemoryError {
+    fn from(error: BackingError) -> Self {
+        MemoryError::MapFailed(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_error_code() {
+        let error = MemoryError::from(crate::backing::out_of_memory());
+
+        assert_eq!(error.code(), ErrorCode::MapFailed);
+        assert_eq!(MemoryError::SizeOverflow.code(), ErrorCode::SizeOverflow);
+        assert!(error.to_string().starts_with("failed to map memory"));
+    }
+}
@@ -0,0 +1,235 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+#[cfg(all(feature = "mmap", unix))]
+use std::io;
+#[cfg(all(feature = "mmap", unix))]
+use std::os::fd::RawFd;
+
+#[cfg(all(feature = "mmap", unix))]
+use crate::error::MemoryError;
+use crate::memory::LinearMemory;
+#[cfg(all(feature = "mmap", unix))]
+use crate::memory::{AccessKind, Operation};
+use crate::ptr::{GuestLayout, PtrError, WasmPtr, WasmSlice};
+
+/// wasm32 `struct iovec`, also WASI's `iovec` and `ciovec`: a buffer pointer and its length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GuestIovec {
+    pub buffer: WasmPtr<u8>,
+    pub length: u32,
+}
+
+// Written out rather than derived, `derive` is an optional feature and iovecs are decoded without it
+impl GuestLayout for GuestIovec {
+    const SIZE: usize = 8;
+    const ALIGN: usize = 4;
+
+    fn load_from(memory: &LinearMemory, address: i32) -> Self {
+        Self {
+            buffer: WasmPtr::load_from(memory, address),
+            length: u32::load_from(memory, address + 4),
+        }
+    }
+
+    fn store_to(&self, memory: &mut LinearMemory, address: i32) {
+        self.buffer.store_to(memory, address);
+        self.length.store_to(memory, address + 4);
+    }
+}
+
+/// Outcome of a scatter/gather transfer. Fewer bytes than requested are transferred when a read
+/// hits end of file or a pipe or socket has less data or room than the iovecs describe.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IoTransfer {
+    pub transferred: usize,
+    pub requested: usize,
+}
+
+impl IoTransfer {
+    pub fn is_partial(&self) -> bool {
+        self.transferred < self.requested
+    }
+}
+
+/// Fails unless `count` iovecs fit in a single `readv` or `writev` call, which rejects more than
+/// `IOV_MAX` of them.
+#[cfg(all(feature = "mmap", unix))]
+fn check_iov_max(count: u32) -> Result<(), MemoryError> {
+    // -1 means the system sets no limit
+    let limit = match unsafe { libc::sysconf(libc::_SC_IOV_MAX) } {
+        -1 => libc::c_int::MAX as libc::c_long,
+        limit => limit,
+    };
+    if i64::from(count) > limit as i64 {
+        return Err(MemoryError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{count} iovecs exceed IOV_MAX of {limit}"),
+        )));
+    }
+    Ok(())
+}
+
+impl LinearMemory {
+    /// Decodes the `count` iovecs at `address` into the byte ranges of their buffers, failing if
+    /// the array or any buffer lies outside the memory.
+    fn iovec_ranges(&self, address: i32, count: u32) -> Result<Vec<Range<usize>>, PtrError> {
+        let offset = u32::try_from(address).map_err(|_| PtrError::OutOfBounds)?;
+        WasmSlice::<GuestIovec>::new(offset, count)
+            .iter(self)?
+            .map(|iovec| {
+                let start =
+                    i32::try_from(iovec.buffer.offset()).map_err(|_| PtrError::OutOfBounds)?;
+                self.checked_range(start, iovec.length as usize)
+                    .ok_or(PtrError::OutOfBounds)
+            })
+            .collect()
+    }
+
+    /// Resolves the `count` iovecs at `address` into the guest buffers they point at.
+    pub fn read_iovecs(&self, address: i32, count: u32) -> Result<Vec<&[u8]>, PtrError> {
+        Ok(self
+            .iovec_ranges(address, count)?
+            .into_iter()
+            .map(|range| self.read_bytes(range.start as i32, range.len()))
+            .collect())
+    }
+
+    /// `readv` from `fd` straight into the guest buffers described by the `count` iovecs at
+    /// `address`. More than `IOV_MAX` iovecs fail with an `InvalidInput` i/o error.
+    #[cfg(all(feature = "mmap", unix))]
+    pub fn readv_from_fd(
+        &mut self,
+        fd: RawFd,
+        address: i32,
+        count: u32,
+    ) -> Result<IoTransfer, MemoryError> {
+        check_iov_max(count)?;
+        let ranges = self.iovec_ranges(address, count)?;
+        let base = self.descriptor().base;
+        let iovecs: Vec<_> = ranges
+            .iter()
+            .map(|range| libc::iovec {
+                iov_base: unsafe { base.add(range.start) }.cast(),
+                iov_len: range.len(),
+            })
+            .collect();
+
+        let transferred =
+            self.observe_ranges(&ranges, AccessKind::Write, Operation::WriteBytes, || {
+                let result =
+                    unsafe { libc::readv(fd, iovecs.as_ptr(), iovecs.len() as libc::c_int) };
+                usize::try_from(result).map_err(|_| MemoryError::Io(io::Error::last_os_error()))
+            })?;
+        Ok(IoTransfer {
+            transferred,
+            requested: ranges.iter().map(Range::len).sum(),
+        })
+    }
+
+    /// `writev` of the guest buffers described by the `count` iovecs at `address` straight to
+    /// `fd`. More than `IOV_MAX` iovecs fail with an `InvalidInput` i/o error.
+    #[cfg(all(feature = "mmap", unix))]
+    pub fn writev_to_fd(
+        &self,
+        fd: RawFd,
+        address: i32,
+        count: u32,
+    ) -> Result<IoTransfer, MemoryError> {
+        check_iov_max(count)?;
+        let buffers = self.read_iovecs(address, count)?;
+        let iovecs: Vec<_> = buffers
+            .iter()
+            .map(|buffer| libc::iovec {
+                iov_base: buffer.as_ptr().cast_mut().cast(),
+                iov_len: buffer.len(),
+            })
+            .collect();
+
+        let result = unsafe { libc::writev(fd, iovecs.as_ptr(), iovecs.len() as libc::c_int) };
+        let transferred =
+            usize::try_from(result).map_err(|_| MemoryError::Io(io::Error::last_os_error()))?;
+        Ok(IoTransfer {
+            transferred,
+            requested: buffers.iter().map(|buffer| buffer.len()).sum(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_iovecs(memory: &mut LinearMemory, address: u32, iovecs: &[(u32, u32)]) {
+        let iovecs: Vec<_> = iovecs
+            .iter()
+            .map(|&(buffer, length)| GuestIovec {
+                buffer: WasmPtr::new(buffer),
+                length,
+            })
+            .collect();
+        WasmSlice::new(address, iovecs.len() as u32)
+            .write(memory, &iovecs)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_read_iovecs() {
+        let mut memory = LinearMemory::new(1);
+        memory.write_bytes(100, b"hello world");
+        write_iovecs(&mut memory, 0, &[(100, 5), (105, 0), (106, 5)]);
+
+        assert_eq!(
+            memory.read_iovecs(0, 3).unwrap(),
+            [&b"hello"[..], b"", b"world"]
+        );
+        let end = memory.size() as u32;
+        write_iovecs(&mut memory, 32, &[(100, 5), (end - 2, 4)]);
+        assert_eq!(memory.read_iovecs(32, 2), Err(PtrError::OutOfBounds));
+        assert_eq!(memory.read_iovecs(2, 1), Err(PtrError::Unaligned));
+    }
+
+    #[cfg(all(feature = "mmap", unix))]
+    #[test]
+    fn test_readv_writev_fd() {
+        use std::io::{Read, Write};
+        use std::os::fd::AsRawFd;
+
+        let mut memory = LinearMemory::new(1);
+        let (mut reader, mut writer) = io::pipe().unwrap();
+        memory.write_bytes(100, b"scatter gather");
+        write_iovecs(&mut memory, 0, &[(100, 8), (108, 6)]);
+
+        let transfer = memory.writev_to_fd(writer.as_raw_fd(), 0, 2).unwrap();
+        assert_eq!(transfer.transferred, 14);
+        assert!(!transfer.is_partial());
+        let mut written = [0; 14];
+        reader.read_exact(&mut written).unwrap();
+        assert_eq!(&written, b"scatter gather");
+
+        // Only six bytes are waiting, the read comes up short of the twelve requested
+        writer.write_all(b"abcdef").unwrap();
+        write_iovecs(&mut memory, 16, &[(200, 4), (300, 8)]);
+        let transfer = memory.readv_from_fd(reader.as_raw_fd(), 16, 2).unwrap();
+        assert_eq!(
+            transfer,
+            IoTransfer {
+                transferred: 6,
+                requested: 12
+            }
+        );
+        assert!(transfer.is_partial());
+        assert_eq!(memory.read_bytes(200, 4), b"abcd");
+        assert_eq!(memory.read_bytes(300, 2), b"ef");
+
+        assert!(matches!(
+            memory.readv_from_fd(-1, 16, 2),
+            Err(MemoryError::Io(_))
+        ));
+        let too_many = memory.writev_to_fd(writer.as_raw_fd(), 0, u32::MAX);
+        assert!(matches!(
+            too_many,
+            Err(MemoryError::Io(error)) if error.kind() == io::ErrorKind::InvalidInput
+        ));
+    }
+}
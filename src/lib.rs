@@ -1,4 +1,32 @@
-#![feature(portable_simd)]
-pub mod ffi;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "portable-simd", feature(portable_simd))]
+extern crate alloc;
+// Lets the derive macros' `::linmem` paths resolve in the crate's own tests
+#[cfg(test)]
+extern crate self as linmem;
+
+#[cfg(feature = "arena")]
+pub mod arena;
+pub mod backing;
+pub mod batch;
+pub mod error;
+pub mod iovec;
+pub mod limits;
 mod macros;
 pub mod memory;
+pub mod protect;
+pub mod ptr;
+#[cfg(feature = "threads")]
+pub mod race;
+#[cfg(feature = "threads")]
+pub mod schedule;
+mod search;
+#[cfg(feature = "std")]
+pub mod shadow;
+pub mod shared;
+pub mod strings;
+pub mod trap;
+#[cfg(feature = "threads")]
+pub mod wait;
+#[cfg(feature = "std")]
+pub mod watchpoint;
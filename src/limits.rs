@@ -0,0 +1,187 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::memory::PAGE_SIZE;
+
+/// Policy consulted before a memory grows. A limiter may be shared by several memories through an
+/// `Arc`, in which case it sees the growth of all of them.
+pub trait ResourceLimiter: Send + Sync {
+    /// Called before a memory of `current` bytes grows to `desired` bytes. Returns the size the
+    /// memory may grow to, `desired` to allow the request or a smaller page multiple to cap it,
+    /// None (or `current`) vetoes it.
+    fn memory_growing(&self, current: usize, desired: usize) -> Option<usize>;
+
+    /// Called when a grow to `granted` bytes which `memory_growing` allowed was cut short, the
+    /// memory ends up `current` bytes long instead. That is its old size when the grow failed, or
+    /// the granted size rounded down to whole pages.
+    fn memory_grow_failed(&self, current: usize, granted: usize) {
+        let _ = (current, granted);
+    }
+
+    /// Called when a memory of `size` bytes stops using the limiter, because it was dropped or
+    /// given another limiter.
+    fn memory_released(&self, size: usize) {
+        let _ = size;
+    }
+}
+
+/// Caps the total bytes of every memory sharing it, for instance all memories of one tenant.
+#[derive(Debug)]
+pub struct MemoryBudget {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Bytes currently held by the memories sharing the budget.
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Acquire)
+    }
+}
+
+impl ResourceLimiter for MemoryBudget {
+    fn memory_growing(&self, current: usize, desired: usize) -> Option<usize> {
+        let page_size = PAGE_SIZE as usize;
+        let mut granted = 0;
+        self.used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                let available = self.limit.saturating_sub(used);
+                granted = (desired - current).min(available / page_size * page_size);
+                (granted > 0).then_some(used + granted)
+            })
+            .ok()?;
+        Some(current + granted)
+    }
+
+    fn memory_grow_failed(&self, current: usize, granted: usize) {
+        self.used.fetch_sub(granted - current, Ordering::AcqRel);
+    }
+
+    fn memory_released(&self, size: usize) {
+        self.used.fetch_sub(size, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::MemoryError;
+    use crate::memory::LinearMemory;
+    use alloc::sync::Arc;
+
+    const PAGE: usize = PAGE_SIZE as usize;
+
+    struct MaxPages(usize);
+
+    impl ResourceLimiter for MaxPages {
+        fn memory_growing(&self, _current: usize, desired: usize) -> Option<usize> {
+            (desired <= self.0 * PAGE).then_some(desired)
+        }
+    }
+
+    #[test]
+    fn test_limiter_vetoes_grow() {
+        let mut memory = LinearMemory::new(1);
+        memory.set_limiter(Arc::new(MaxPages(2))).unwrap();
+
+        assert_eq!(memory.try_grow(1).unwrap(), 1);
+        assert!(matches!(
+            memory.try_grow(1),
+            Err(MemoryError::LimitExceeded)
+        ));
+        assert_eq!(memory.size(), 2 * PAGE);
+        assert!(memory.set_limiter(Arc::new(MaxPages(1))).is_err());
+    }
+
+    #[test]
+    fn test_budget_shared_by_group() {
+        let budget = Arc::new(MemoryBudget::new(5 * PAGE));
+        let mut first = LinearMemory::new(2);
+        let mut second = LinearMemory::new(2);
+        first.set_limiter(budget.clone()).unwrap();
+        second.set_limiter(budget.clone()).unwrap();
+        assert_eq!(budget.used(), 4 * PAGE);
+
+        // Capped to the single page left in the budget
+        assert_eq!(first.try_grow(3).unwrap(), 1);
+        assert_eq!(first.size(), 3 * PAGE);
+        assert!(!second.grow(1));
+        assert_eq!(budget.used(), 5 * PAGE);
+
+        drop(first);
+        assert_eq!(budget.used(), 2 * PAGE);
+        assert!(second.grow(3));
+        assert!(LinearMemory::new(1).set_limiter(budget.clone()).is_err());
+        assert_eq!(budget.used(), 5 * PAGE);
+    }
+
+    /// Grants `extra` bytes past the current size and records what is handed back.
+    struct Ragged {
+        extra: usize,
+        refunded: AtomicUsize,
+    }
+
+    impl ResourceLimiter for Ragged {
+        fn memory_growing(&self, current: usize, _desired: usize) -> Option<usize> {
+            Some(current + self.extra)
+        }
+
+        fn memory_grow_failed(&self, current: usize, granted: usize) {
+            self.refunded.fetch_add(granted - current, Ordering::AcqRel);
+        }
+    }
+
+    #[test]
+    fn test_partial_page_grant_is_rounded_down() {
+        let ragged = |extra| {
+            Arc::new(Ragged {
+                extra,
+                refunded: AtomicUsize::new(0),
+            })
+        };
+        let mut memory = LinearMemory::new(0);
+        let limiter = ragged(PAGE + 10);
+        memory.set_limiter(limiter.clone()).unwrap();
+
+        assert_eq!(memory.try_grow(2).unwrap(), 1);
+        assert_eq!(memory.size(), PAGE);
+        assert_eq!(limiter.refunded.load(Ordering::Acquire), 10);
+
+        let mut memory = LinearMemory::new(0);
+        let limiter = ragged(10);
+        memory.set_limiter(limiter.clone()).unwrap();
+        assert!(matches!(
+            memory.try_grow(1),
+            Err(MemoryError::LimitExceeded)
+        ));
+        assert_eq!(memory.size(), 0);
+        assert_eq!(limiter.refunded.load(Ordering::Acquire), 10);
+    }
+
+    #[test]
+    fn test_failed_grow_refunds_budget() {
+        let budget = Arc::new(MemoryBudget::new(usize::MAX));
+        let mut memory = LinearMemory::with_backing(unsafe {
+            crate::backing::ExternalBacking::new(
+                core::ptr::NonNull::<u64>::dangling().as_ptr().cast(),
+                0,
+                0,
+            )
+            .unwrap()
+        });
+        memory.set_limiter(budget.clone()).unwrap();
+
+        assert!(!memory.grow(1));
+        assert_eq!(budget.used(), 0);
+    }
+}
@@ -20,11 +20,14 @@ macro_rules! make_readers {
         #[must_use]
         pub fn $fn_name(&self, address: i32) -> $read_type {
             const BYTE_COUNT: usize = size_of::<$address_type>();
-            // Safety we assume the params passed are correct
-            unsafe {
-                let pointer = self.memory.as_ptr().add(address as usize).cast::<[u8; BYTE_COUNT]>();
-                <$address_type>::from_le_bytes(std::ptr::read_unaligned(pointer)) as $read_type
-            }
+            let start = self.checked_start(address, BYTE_COUNT);
+            self.observe(address, BYTE_COUNT, AccessKind::Read, Operation::Load, || {
+                // Safety the range was checked to lie within the memory
+                unsafe {
+                    let pointer = self.memory.as_ptr().add(start).cast::<[u8; BYTE_COUNT]>();
+                    <$address_type>::from_le_bytes(core::ptr::read_unaligned(pointer)) as $read_type
+                }
+            })
         }
     };
 
@@ -45,11 +48,17 @@ macro_rules! make_readers {
     (@single (@atomic $fn_name:ident, $read_type:ty, $address_type:ty, $address_type_non_atomic: ty)) => {
         #[must_use]
         pub fn $fn_name(&self, address: i32) -> $read_type {
-            // Safety we assume the params passed are correct
-            unsafe {
-                let pointer = self.memory.as_ptr().add(address as usize).cast::<$address_type>();
-                (*pointer).load(Ordering::SeqCst) as $read_type
-            }
+            const BYTE_COUNT: usize = size_of::<$address_type>();
+            let start = self
+                .atomic_start(address, BYTE_COUNT)
+                .unwrap_or_else(|trap| trap::raise(trap));
+            self.observe(address, BYTE_COUNT, AccessKind::Read, Operation::AtomicLoad, || {
+                // Safety the range was checked to lie within the memory and be aligned
+                unsafe {
+                    let pointer = self.memory.as_ptr().add(start).cast::<$address_type>();
+                    (*pointer).load(Ordering::SeqCst) as $read_type
+                }
+            })
         }
     };
 }
@@ -75,12 +84,15 @@ macro_rules! make_writers {
     (@single ($fn_name:ident, $write_type:ty, $address_type:ty)) => {
         pub fn $fn_name(&mut self, address: i32, value: $write_type) {
             const BYTE_COUNT: usize = size_of::<$address_type>();
-            // Safety we assume the params passed are correct
-            unsafe {
-                let write_val = (value as $address_type).to_le_bytes();
-                let pointer = self.memory.as_mut_ptr().add(address as usize).cast::<[u8; BYTE_COUNT]>();
-                std::ptr::write_unaligned(pointer, write_val)
-            }
+            let write_val = (value as $address_type).to_le_bytes();
+            let start = self.checked_start(address, BYTE_COUNT);
+            // Safety the range was checked to lie within the memory
+            let pointer = unsafe {
+                self.memory.as_mut_ptr().add(start).cast::<[u8; BYTE_COUNT]>()
+            };
+            self.observe(address, BYTE_COUNT, AccessKind::Write, Operation::Store, || unsafe {
+                core::ptr::write_unaligned(pointer, write_val)
+            })
         }
     };
 
@@ -100,11 +112,17 @@ macro_rules! make_writers {
 
     (@single (@atomic $fn_name:ident, $write_type:ty, $address_type:ty, $address_type_non_atomic: ty)) => {
         pub fn $fn_name(&self, address: i32, value: $write_type) {
-            // Safety we assume the params passed are correct
-            unsafe {
-                let pointer = self.memory.as_ptr().add(address as usize).cast::<$address_type>();
-                (*pointer).store(value as $address_type_non_atomic, Ordering::SeqCst);
-            }
+            const BYTE_COUNT: usize = size_of::<$address_type>();
+            let start = self
+                .atomic_start(address, BYTE_COUNT)
+                .unwrap_or_else(|trap| trap::raise(trap));
+            self.observe(address, BYTE_COUNT, AccessKind::Write, Operation::AtomicStore, || {
+                // Safety the range was checked to lie within the memory and be aligned
+                unsafe {
+                    let pointer = self.memory.as_ptr().add(start).cast::<$address_type>();
+                    (*pointer).store(value as $address_type_non_atomic, Ordering::SeqCst);
+                }
+            })
         }
     };
 }
@@ -118,3 +136,65 @@ macro_rules! make_read_writers {
         )*
     };
 }
+
+#[macro_export]
+macro_rules! make_array_read_writers {
+    ($($value_type:ty),* $(,)?) => {
+        $(
+            paste! {
+                make_array_read_writers!(@single [<read_ $value_type _array>], [<write_ $value_type _array>], $value_type);
+            }
+        )*
+    };
+
+    (@single $read_name:ident, $write_name:ident, $value_type:ty) => {
+        /// Fills `out` with consecutive little endian values starting at `address`, returns
+        /// false without reading if the range exceeds the memory bounds.
+        pub fn $read_name(&self, address: i32, out: &mut [$value_type]) -> bool {
+            const BYTE_COUNT: usize = size_of::<$value_type>();
+            let Some(range) = out
+                .len()
+                .checked_mul(BYTE_COUNT)
+                .and_then(|byte_count| self.checked_range(address, byte_count))
+            else {
+                return false;
+            };
+
+            let bytes = self.read_bytes(address, range.len());
+            if cfg!(target_endian = "little") {
+                // Safety the destination is exactly bytes.len() long and any bit pattern is valid
+                unsafe {
+                    core::ptr::copy_nonoverlapping(bytes.as_ptr(), out.as_mut_ptr().cast::<u8>(), bytes.len());
+                }
+            } else {
+                for (value, chunk) in out.iter_mut().zip(bytes.chunks_exact(BYTE_COUNT)) {
+                    *value = <$value_type>::from_le_bytes(chunk.try_into().unwrap());
+                }
+            }
+            true
+        }
+
+        /// Writes `values` little endian starting at `address`, returns false without writing if
+        /// the range exceeds the memory bounds.
+        pub fn $write_name(&mut self, address: i32, values: &[$value_type]) -> bool {
+            const BYTE_COUNT: usize = size_of::<$value_type>();
+            let Some(range) = values
+                .len()
+                .checked_mul(BYTE_COUNT)
+                .and_then(|byte_count| self.checked_range(address, byte_count))
+            else {
+                return false;
+            };
+
+            if cfg!(target_endian = "little") {
+                // Safety reinterpreting plain values as bytes of the same total length
+                let bytes = unsafe { slice::from_raw_parts(values.as_ptr().cast::<u8>(), range.len()) };
+                self.write_bytes(address, bytes);
+            } else {
+                let bytes: alloc::vec::Vec<u8> = values.iter().flat_map(|value| value.to_le_bytes()).collect();
+                self.write_bytes(address, &bytes);
+            }
+            true
+        }
+    };
+}
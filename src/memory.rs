@@ -1,80 +1,620 @@
 #![allow(clippy::missing_safety_doc)]
-use concurrent_queue::ConcurrentQueue;
-use dashmap::DashMap;
-#[cfg(not(target_os = "linux"))]
-use memmap2::{MmapMut, MmapOptions};
-#[cfg(target_os = "linux")]
-use memmap2::{MmapMut, MmapOptions, RemapOptions};
-use parking_lot::{Condvar, Mutex};
-use paste::paste;
-use std::simd::{cmp::SimdPartialEq, Simd};
-use std::sync::atomic::{
-    AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicU16, AtomicU32, AtomicU8, Ordering,
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cmp::Ordering as CmpOrdering;
+use core::ops::Range;
+use core::sync::atomic::{
+    AtomicBool, AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicU16, AtomicU32, AtomicU8, Ordering,
 };
-use std::sync::Arc;
+use core::{ptr, slice};
+use paste::paste;
+#[cfg(feature = "threads")]
 use std::time::{Duration, Instant};
-use std::{ptr, slice};
 
-use crate::{make_read_writers, make_readers, make_writers};
+use crate::backing::Backing;
+#[cfg(not(feature = "mmap"))]
+use crate::backing::HeapBacking;
+#[cfg(feature = "mmap")]
+use crate::backing::MmapBacking;
+use crate::error::MemoryError;
+use crate::limits::ResourceLimiter;
+use crate::protect::{PageProtections, Protection};
+#[cfg(feature = "threads")]
+use crate::race::{DataRace, RaceDetector, SyncKey};
+#[cfg(feature = "threads")]
+use crate::schedule::DeterministicScheduler;
+use crate::search;
+#[cfg(feature = "std")]
+use crate::shadow::{ShadowMemory, ShadowState, ShadowViolation};
+use crate::trap::{self, Trap};
+#[cfg(feature = "threads")]
+use crate::wait::{WaitAsync, WaitHandle, WaitQueues, WAIT_NOT_EQUAL, WAIT_OK, WAIT_TERMINATED};
+#[cfg(feature = "std")]
+use crate::watchpoint::{WatchEvent, WatchpointId, Watchpoints};
+use crate::{make_array_read_writers, make_read_writers, make_readers, make_writers};
+
+pub(crate) const PAGE_SIZE: u32 = 64 * 1024;
+
+fn page_bytes(pages: u32) -> Result<usize, MemoryError> {
+    (pages as usize)
+        .checked_mul(PAGE_SIZE as usize)
+        .ok_or(MemoryError::SizeOverflow)
+}
+
+/// Byte count of a bulk operation, trapping with `Trap::OutOfBounds` if it is negative.
+fn checked_byte_count(byte_count: i32) -> usize {
+    usize::try_from(byte_count).unwrap_or_else(|_| trap::raise(Trap::OutOfBounds))
+}
+
+/// Direction of a memory access, also used to select which accesses a watchpoint observes.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessKind {
+    Read = 1,
+    Write = 2,
+    ReadWrite = 3,
+}
 
-const PAGE_SIZE: u32 = 64 * 1024;
-const VECTOR_SIZE: usize = 16;
+impl TryFrom<u32> for AccessKind {
+    type Error = MemoryError;
 
-type WaitQueue = Arc<ConcurrentQueue<Arc<WaitEntry>>>;
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(AccessKind::Read),
+            2 => Ok(AccessKind::Write),
+            3 => Ok(AccessKind::ReadWrite),
+            _ => Err(MemoryError::InvalidArgument),
+        }
+    }
+}
 
-#[derive(Debug)]
-struct WaitEntry {
-    condvar: Condvar,
-    mutex: Mutex<()>,
+impl AccessKind {
+    #[cfg(feature = "std")]
+    pub(crate) fn overlaps(self, other: AccessKind) -> bool {
+        (self as u8) & (other as u8) != 0
+    }
+}
+
+/// The memory instruction (or host call) responsible for an access.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Load,
+    Store,
+    AtomicLoad,
+    AtomicStore,
+    AtomicRmw,
+    AtomicCompareExchange,
+    Copy,
+    Fill,
+    ReadBytes,
+    WriteBytes,
+}
+
+/// Snapshot of where the memory lives for hosts that access it directly. The base pointer stays
+/// valid until `generation` changes, direct accesses bypass watchpoints, the shadow map and the
+/// race detector.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryDescriptor {
+    pub base: *mut u8,
+    pub byte_length: usize,
+    pub generation: u64,
+}
+
+impl Default for MemoryDescriptor {
+    fn default() -> Self {
+        Self {
+            base: ptr::null_mut(),
+            byte_length: 0,
+            generation: 0,
+        }
+    }
+}
+
+pub type GrowListenerId = u32;
+
+type GrowListener = Box<dyn Fn(&GrowEvent) + Send + Sync>;
+
+/// Passed to grow listeners after a successful grow. `old_base` may no longer be valid, it is
+/// only meant for comparison with pointers a host has cached.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GrowEvent {
+    pub old_base: *mut u8,
+    pub old_size: usize,
+    pub new_base: *mut u8,
+    pub new_size: usize,
 }
 
 pub struct LinearMemory {
-    memory: MmapMut,
-    wait_queues: DashMap<i32, WaitQueue>,
+    memory: Box<dyn Backing>,
+    generation: u64,
+    faulted: AtomicBool,
+    protections: PageProtections,
+    limiter: Option<Arc<dyn ResourceLimiter>>,
+    grow_listeners: Vec<(GrowListenerId, GrowListener)>,
+    next_grow_listener: GrowListenerId,
+    #[cfg(feature = "threads")]
+    wait_queues: WaitQueues,
+    #[cfg(feature = "std")]
+    watchpoints: Watchpoints,
+    #[cfg(feature = "std")]
+    shadow: Option<ShadowMemory>,
+    #[cfg(feature = "threads")]
+    race_detector: Option<RaceDetector>,
+    #[cfg(feature = "threads")]
+    scheduler: Option<Arc<DeterministicScheduler>>,
 }
 
 impl LinearMemory {
     pub fn new(pages: u32) -> Self {
-        let memory = MmapOptions::new()
-            .len((pages * PAGE_SIZE) as usize)
-            .map_anon()
-            .expect("Failed to create memory map");
+        Self::try_new(pages).expect("Failed to create memory map")
+    }
+
+    /// Creates a memory of `pages` zeroed pages over an anonymous mapping, or over the heap when
+    /// the `mmap` feature is disabled.
+    pub fn try_new(pages: u32) -> Result<Self, MemoryError> {
+        #[cfg(feature = "mmap")]
+        let backing = MmapBacking::new(page_bytes(pages)?)?;
+        #[cfg(not(feature = "mmap"))]
+        let backing = {
+            let mut backing = HeapBacking::default();
+            backing.grow(page_bytes(pages)?)?;
+            backing
+        };
+        Ok(Self::with_backing(backing))
+    }
 
+    /// Creates a memory over `backing`, its current length becomes the size of the memory.
+    pub fn with_backing(backing: impl Backing + 'static) -> Self {
         Self {
-            memory,
-            wait_queues: DashMap::new(),
+            memory: Box::new(backing),
+            generation: 0,
+            faulted: AtomicBool::new(false),
+            protections: PageProtections::default(),
+            limiter: None,
+            grow_listeners: Vec::new(),
+            next_grow_listener: 0,
+            #[cfg(feature = "threads")]
+            wait_queues: WaitQueues::default(),
+            #[cfg(feature = "std")]
+            watchpoints: Watchpoints::default(),
+            #[cfg(feature = "std")]
+            shadow: None,
+            #[cfg(feature = "threads")]
+            race_detector: None,
+            #[cfg(feature = "threads")]
+            scheduler: None,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    /// Registers `callback` to be invoked after every access of `kind` that touches `range`.
+    pub fn add_watchpoint(
+        &self,
+        range: Range<i32>,
+        kind: AccessKind,
+        callback: impl Fn(&WatchEvent) + Send + Sync + 'static,
+    ) -> WatchpointId {
+        self.watchpoints.add(range, kind, Arc::new(callback))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn remove_watchpoint(&self, id: WatchpointId) -> bool {
+        self.watchpoints.remove(id)
+    }
+
+    #[cfg(feature = "std")]
+    /// Starts tracking which bytes are addressable, every existing byte begins addressable.
+    pub fn enable_shadow(&mut self) {
+        if self.shadow.is_none() {
+            self.shadow = Some(ShadowMemory::new(self.memory.len()));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    /// Marks `range` with `state` in the shadow map, does nothing unless the shadow is enabled.
+    pub fn poison(&self, range: Range<i32>, state: ShadowState) {
+        if let Some(shadow) = &self.shadow {
+            shadow.set(range.start as usize..range.end as usize, state);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn unpoison(&self, range: Range<i32>) {
+        self.poison(range, ShadowState::Addressable);
+    }
+
+    #[cfg(feature = "std")]
+    /// Returns the oldest shadow violation which has not yet been taken. At most
+    /// `MAX_SHADOW_VIOLATIONS` wait to be taken, see `dropped_shadow_violations`.
+    pub fn take_shadow_violation(&self) -> Option<ShadowViolation> {
+        self.shadow.as_ref().and_then(ShadowMemory::take_violation)
+    }
+
+    #[cfg(feature = "std")]
+    /// Number of violations discarded because `MAX_SHADOW_VIOLATIONS` were already waiting.
+    pub fn dropped_shadow_violations(&self) -> u64 {
+        self.shadow
+            .as_ref()
+            .map_or(0, ShadowMemory::dropped_violations)
+    }
+
+    #[cfg(feature = "threads")]
+    /// Starts checking non-atomic accesses from different host threads for happens-before
+    /// ordering. Atomics, `wait_*`/`notify` and `atomic_fence` synchronise threads.
+    pub fn enable_race_detector(&mut self) {
+        if self.race_detector.is_none() {
+            self.race_detector = Some(RaceDetector::default());
+        }
+    }
+
+    #[cfg(feature = "threads")]
+    /// Publishes the calling thread's history under `key`, for host side synchronisation the
+    /// detector can't see such as spawning or joining a thread.
+    pub fn race_release(&self, key: u64) {
+        if let Some(detector) = &self.race_detector {
+            detector.release(SyncKey::Host(key));
+        }
+    }
+
+    #[cfg(feature = "threads")]
+    /// Orders the calling thread after every `race_release` of `key` which came before it.
+    pub fn race_acquire(&self, key: u64) {
+        if let Some(detector) = &self.race_detector {
+            detector.acquire(SyncKey::Host(key));
         }
     }
 
-    #[cfg(target_os = "linux")]
+    #[cfg(feature = "threads")]
+    /// Hands threads entered into `scheduler` over to it at every atomic, wait and notify, so
+    /// their interleaving and wait outcomes repeat exactly for the same seed.
+    pub fn set_scheduler(&mut self, scheduler: Arc<DeterministicScheduler>) {
+        self.scheduler = Some(scheduler);
+    }
+
+    #[cfg(feature = "threads")]
+    /// Returns the oldest data race which has not yet been taken. At most `MAX_DATA_RACES` wait
+    /// to be taken, see `dropped_data_races`.
+    pub fn take_data_race(&self) -> Option<DataRace> {
+        self.race_detector
+            .as_ref()
+            .and_then(RaceDetector::take_race)
+    }
+
+    #[cfg(feature = "threads")]
+    /// Number of races discarded because `MAX_DATA_RACES` were already waiting.
+    pub fn dropped_data_races(&self) -> u64 {
+        self.race_detector
+            .as_ref()
+            .map_or(0, RaceDetector::dropped_races)
+    }
+
+    #[inline(always)]
+    fn observe<R>(
+        &self,
+        address: i32,
+        byte_count: usize,
+        kind: AccessKind,
+        operation: Operation,
+        access: impl FnOnce() -> R,
+    ) -> R {
+        #[cfg(feature = "threads")]
+        if let Some(scheduler) = &self.scheduler {
+            if matches!(
+                operation,
+                Operation::AtomicLoad
+                    | Operation::AtomicStore
+                    | Operation::AtomicRmw
+                    | Operation::AtomicCompareExchange
+            ) {
+                scheduler.yield_now();
+            }
+        }
+        if self.protections.is_active() {
+            let start = address as usize;
+            if !self.protections.allows(start..start + byte_count, kind) {
+                trap::raise(Trap::Protected);
+            }
+        }
+        #[cfg(feature = "std")]
+        if let Some(shadow) = &self.shadow {
+            shadow.check(address, byte_count, kind, operation);
+        }
+        #[cfg(feature = "threads")]
+        let access = || match &self.race_detector {
+            Some(detector) => detector.observe(address, byte_count, kind, operation, access),
+            None => access(),
+        };
+        #[cfg(feature = "std")]
+        if self.watchpoints.is_active() {
+            let bytes = || unsafe {
+                slice::from_raw_parts(self.memory.as_ptr().add(address as usize), byte_count)
+            };
+            return self
+                .watchpoints
+                .observe(address, byte_count, kind, operation, bytes, access);
+        }
+        let _ = (address, byte_count, kind, operation);
+        access()
+    }
+
+    /// Observes an access to each of `ranges` around `access`, which touches all of them at once.
+    #[cfg(all(feature = "mmap", unix))]
+    pub(crate) fn observe_ranges<R>(
+        &self,
+        ranges: &[Range<usize>],
+        kind: AccessKind,
+        operation: Operation,
+        access: impl FnOnce() -> R,
+    ) -> R {
+        match ranges.split_first() {
+            Some((range, rest)) => {
+                self.observe(range.start as i32, range.len(), kind, operation, || {
+                    self.observe_ranges(rest, kind, operation, access)
+                })
+            }
+            None => access(),
+        }
+    }
+
+    #[inline(always)]
+    fn atomic<A, R>(&self, address: i32, operation: Operation, access: impl FnOnce(&A) -> R) -> R {
+        let start = self
+            .atomic_start(address, size_of::<A>())
+            .unwrap_or_else(|trap| trap::raise(trap));
+        let aligned_ptr = self.memory[start..].as_ptr() as *const A;
+        let kind = match operation {
+            Operation::AtomicLoad => AccessKind::Read,
+            Operation::AtomicStore => AccessKind::Write,
+            _ => AccessKind::ReadWrite,
+        };
+        self.observe(address, size_of::<A>(), kind, operation, || unsafe {
+            access(&*aligned_ptr)
+        })
+    }
+
     pub fn grow(&mut self, pages: u32) -> bool {
-        let additional_size = (pages * PAGE_SIZE) as usize;
-        let current_size = self.memory.len();
-        let new_size = current_size + additional_size;
+        self.try_grow(pages).is_ok()
+    }
+
+    /// Grows the memory by `pages`, or by fewer if a resource limiter caps the request. Returns
+    /// the number of pages added. An error restoring page protections after the memory grew is
+    /// returned once the grow has been recorded and the grow listeners have run.
+    pub fn try_grow(&mut self, pages: u32) -> Result<u32, MemoryError> {
+        let current = self.memory.len();
+        let mut new_size = current
+            .checked_add(page_bytes(pages)?)
+            .ok_or(MemoryError::SizeOverflow)?;
+        if let Some(limiter) = self.limiter.as_ref().filter(|_| pages > 0) {
+            let granted = limiter
+                .memory_growing(current, new_size)
+                .filter(|&granted| granted > current)
+                .ok_or(MemoryError::LimitExceeded)?;
+            // Only whole pages are added, whatever else was granted goes back to the limiter
+            let page_size = PAGE_SIZE as usize;
+            let usable = granted.min(new_size) / page_size * page_size;
+            if usable <= current {
+                limiter.memory_grow_failed(current, granted);
+                return Err(MemoryError::LimitExceeded);
+            }
+            if usable < granted {
+                limiter.memory_grow_failed(usable, granted);
+            }
+            new_size = usable;
+        }
+
+        // A remap can't span pages with different protections, so lift them for the grow
+        let protected = self.protections.is_active();
+        let old_base = self.memory.as_mut_ptr();
+        let lifted = if protected {
+            self.memory.protect(0..current, Protection::ReadWrite)
+        } else {
+            Ok(())
+        };
+        if let Err(error) = lifted.and_then(|()| self.memory.grow(new_size)) {
+            if let Some(limiter) = &self.limiter {
+                limiter.memory_grow_failed(current, new_size);
+            }
+            if protected {
+                self.apply_protections()?;
+            }
+            return Err(error.into());
+        }
+
+        // The memory has grown even if its protections can't be restored below, so everything
+        // tracking its size is updated before reporting that
+        self.generation += 1;
+        self.protections.resize(new_size / PAGE_SIZE as usize);
+        #[cfg(feature = "std")]
+        if let Some(shadow) = &mut self.shadow {
+            shadow.resize(new_size);
+        }
+
+        let event = GrowEvent {
+            old_base,
+            old_size: current,
+            new_base: self.memory.as_mut_ptr(),
+            new_size,
+        };
+        for (_, listener) in &self.grow_listeners {
+            listener(&event);
+        }
+        if protected {
+            self.apply_protections()?;
+        }
+        Ok(((new_size - current) / PAGE_SIZE as usize) as u32)
+    }
+
+    /// Registers `listener` to be called after every successful grow, so hosts can refresh cached
+    /// base pointers and lengths. Listeners run in registration order.
+    pub fn add_grow_listener(
+        &mut self,
+        listener: impl Fn(&GrowEvent) + Send + Sync + 'static,
+    ) -> GrowListenerId {
+        self.next_grow_listener += 1;
+        self.grow_listeners
+            .push((self.next_grow_listener, Box::new(listener)));
+        self.next_grow_listener
+    }
+
+    pub fn remove_grow_listener(&mut self, id: GrowListenerId) -> bool {
+        let before = self.grow_listeners.len();
+        self.grow_listeners
+            .retain(|(listener_id, _)| *listener_id != id);
+        self.grow_listeners.len() != before
+    }
+
+    /// Makes `limiter` decide every later grow. The memory's current size is charged to it first,
+    /// and the limiter is not installed if it refuses that. A previous limiter is released.
+    pub fn set_limiter(&mut self, limiter: Arc<dyn ResourceLimiter>) -> Result<(), MemoryError> {
+        let size = self.memory.len();
+        if size > 0 {
+            match limiter.memory_growing(0, size) {
+                Some(granted) if granted >= size => {
+                    if granted > size {
+                        limiter.memory_grow_failed(size, granted);
+                    }
+                }
+                Some(granted) => {
+                    limiter.memory_grow_failed(0, granted);
+                    return Err(MemoryError::LimitExceeded);
+                }
+                None => return Err(MemoryError::LimitExceeded),
+            }
+        }
+        if let Some(previous) = self.limiter.replace(limiter) {
+            previous.memory_released(size);
+        }
+        Ok(())
+    }
+
+    /// Sets the protection of a range of pages, as `memory.protect` from the memory-control
+    /// proposal. Accesses made through the memory which `protection` forbids trap with
+    /// `Trap::Protected`. Direct accesses through a descriptor are not trapped: backings over mmap
+    /// also apply the protection in hardware, so such an access raises `SIGSEGV` or `SIGBUS`,
+    /// which linmem installs no handler for.
+    pub fn protect(
+        &mut self,
+        pages: Range<u32>,
+        protection: Protection,
+    ) -> Result<(), MemoryError> {
+        let page_count = self.memory.len() / PAGE_SIZE as usize;
+        let pages = pages.start as usize..pages.end as usize;
+        if pages.start > pages.end || pages.end > page_count {
+            return Err(MemoryError::InvalidRange);
+        }
+        let page_size = PAGE_SIZE as usize;
 
-        unsafe {
+        self.memory
+            .protect(pages.start * page_size..pages.end * page_size, protection)?;
+        self.protections.set(page_count, pages, protection);
+        Ok(())
+    }
+
+    fn apply_protections(&mut self) -> Result<(), MemoryError> {
+        let page_size = PAGE_SIZE as usize;
+        for (pages, protection) in self.protections.restricted_runs() {
             self.memory
-                .remap(new_size, RemapOptions::new().may_move(true))
-                .is_ok()
+                .protect(pages.start * page_size..pages.end * page_size, protection)?;
         }
+        Ok(())
     }
 
-    #[cfg(not(target_os = "linux"))]
-    pub fn grow(&mut self, pages: u32) -> bool {
-        let additional_size = (pages * PAGE_SIZE) as usize;
-        let current_size = self.memory.len();
-        let new_size = current_size + additional_size;
+    /// True if no protected page forbids an access of `kind` to `range`.
+    pub(crate) fn is_accessible(&self, range: Range<usize>, kind: AccessKind) -> bool {
+        !self.protections.is_active() || self.protections.allows(range, kind)
+    }
+
+    /// Current size of the memory in bytes.
+    pub fn size(&self) -> usize {
+        self.memory.len()
+    }
 
-        let mut new_memory = memmap2::MmapOptions::new()
-            .len(new_size)
-            .map_anon()
-            .expect("Failed to create a new memory map");
+    /// Incremented by every successful grow, which may move the base of the memory.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// True once a panic was caught at the FFI boundary while this memory was in use.
+    pub fn is_faulted(&self) -> bool {
+        self.faulted.load(Ordering::Acquire)
+    }
 
-        new_memory[..current_size].copy_from_slice(&self.memory[..current_size]);
-        self.memory = new_memory;
-        true
+    pub fn clear_fault(&self) {
+        self.faulted.store(false, Ordering::Release);
     }
 
+    /// Zeroes every byte keeping the size, the shadow map returns to addressable and every page
+    /// to read write. Fails without zeroing anything if the page protections can't be lifted.
+    pub fn reset(&mut self) -> Result<(), MemoryError> {
+        if self.protections.is_active() {
+            let len = self.memory.len();
+            self.memory.protect(0..len, Protection::ReadWrite)?;
+            self.protections.clear();
+        }
+        self.memory.reset();
+        #[cfg(feature = "std")]
+        if let Some(shadow) = &self.shadow {
+            shadow.set(0..self.memory.len(), ShadowState::Addressable);
+        }
+        Ok(())
+    }
+
+    /// Marks the memory as faulted, called by embedders that contain a panic mid access.
+    pub fn fault(&self) {
+        self.faulted.store(true, Ordering::Release);
+    }
+
+    pub fn descriptor(&mut self) -> MemoryDescriptor {
+        MemoryDescriptor {
+            base: self.memory.as_mut_ptr(),
+            byte_length: self.memory.len(),
+            generation: self.generation,
+        }
+    }
+
+    /// Returns the byte range covered by an access if it lies entirely within the memory.
+    pub(crate) fn checked_range(&self, address: i32, byte_count: usize) -> Option<Range<usize>> {
+        let start = usize::try_from(address).ok()?;
+        let end = start.checked_add(byte_count)?;
+        (end <= self.memory.len()).then_some(start..end)
+    }
+
+    #[cfg(feature = "std")]
+    /// Which bytes of `range` a copy carries over as initialised, all of them without a shadow.
+    fn initialised(&self, range: Range<usize>) -> Vec<bool> {
+        match &self.shadow {
+            Some(shadow) => shadow.initialised(range),
+            None => vec![true; range.len()],
+        }
+    }
+
+    /// Start of the `byte_count` bytes at `address`, trapping with `Trap::OutOfBounds` if they
+    /// exceed the memory.
+    fn checked_start(&self, address: i32, byte_count: usize) -> usize {
+        self.checked_range(address, byte_count)
+            .unwrap_or_else(|| trap::raise(Trap::OutOfBounds))
+            .start
+    }
+
+    /// Start of an atomic access of `width` bytes at `address`, which must lie within the memory
+    /// and be a multiple of `width`.
+    pub(crate) fn atomic_start(&self, address: i32, width: usize) -> Result<usize, Trap> {
+        let start = self
+            .checked_range(address, width)
+            .ok_or(Trap::OutOfBounds)?
+            .start;
+        if !start.is_multiple_of(width) {
+            return Err(Trap::UnalignedAtomic);
+        }
+        Ok(start)
+    }
+
+    /// Copies `byte_count` bytes from `src_offset` to `dest_offset` in `dest_memory`, trapping
+    /// with `Trap::OutOfBounds` if either range exceeds its memory.
     pub fn copy(
         &self,
         src_offset: i32,
@@ -82,65 +622,155 @@ impl LinearMemory {
         dest_offset: i32,
         byte_count: i32,
     ) {
-        let src_ptr = self.memory.as_ptr().wrapping_add(src_offset as usize);
-        let dest_ptr = dest_memory
-            .memory
-            .as_mut_ptr()
-            .wrapping_add(dest_offset as usize);
-
-        debug_assert!(
-            (src_offset as usize) + (byte_count as usize) <= self.memory.len(),
-            "Source range exceeds memory bounds"
-        );
-        debug_assert!(
-            (dest_offset as usize) + (byte_count as usize) <= dest_memory.memory.len(),
-            "Destination range exceeds memory bounds"
+        let byte_count = checked_byte_count(byte_count);
+        let src_start = self.checked_start(src_offset, byte_count);
+        let dest_start = dest_memory.checked_start(dest_offset, byte_count);
+        let src_ptr = unsafe { self.memory.as_ptr().add(src_start) };
+        let dest_ptr = unsafe { dest_memory.memory.as_mut_ptr().add(dest_start) };
+        #[cfg(feature = "std")]
+        let initialised = dest_memory
+            .shadow
+            .is_some()
+            .then(|| self.initialised(src_start..src_start + byte_count));
+
+        self.observe(
+            src_offset,
+            byte_count,
+            AccessKind::Read,
+            Operation::Copy,
+            || {
+                dest_memory.observe(
+                    dest_offset,
+                    byte_count,
+                    AccessKind::Write,
+                    Operation::Copy,
+                    || unsafe { ptr::copy(src_ptr, dest_ptr, byte_count) },
+                )
+            },
         );
+        #[cfg(feature = "std")]
+        if let (Some(shadow), Some(initialised)) = (&dest_memory.shadow, initialised) {
+            shadow.set_initialised(dest_start, &initialised);
+        }
+    }
 
-        unsafe {
-            ptr::copy(src_ptr, dest_ptr, byte_count as usize);
+    /// Copies `byte_count` bytes from `src_offset` to `dest_offset` within this memory, the ranges
+    /// may overlap. Traps with `Trap::OutOfBounds` if either range exceeds the memory.
+    pub fn copy_within(&mut self, src_offset: i32, dest_offset: i32, byte_count: i32) {
+        let byte_count = checked_byte_count(byte_count);
+        let src_start = self.checked_start(src_offset, byte_count);
+        let dest_start = self.checked_start(dest_offset, byte_count);
+
+        let base = self.memory.as_mut_ptr();
+        #[cfg(feature = "std")]
+        let initialised = self
+            .shadow
+            .is_some()
+            .then(|| self.initialised(src_start..src_start + byte_count));
+        self.observe(
+            src_offset,
+            byte_count,
+            AccessKind::Read,
+            Operation::Copy,
+            || {
+                self.observe(
+                    dest_offset,
+                    byte_count,
+                    AccessKind::Write,
+                    Operation::Copy,
+                    || unsafe { ptr::copy(base.add(src_start), base.add(dest_start), byte_count) },
+                )
+            },
+        );
+        #[cfg(feature = "std")]
+        if let (Some(shadow), Some(initialised)) = (&self.shadow, initialised) {
+            shadow.set_initialised(dest_start, &initialised);
         }
     }
 
+    /// Sets `byte_count` bytes from `offset` to `value`, trapping with `Trap::OutOfBounds` if
+    /// they exceed the memory.
     pub fn fill(&mut self, offset: i32, byte_count: i32, value: u8) {
-        let start = offset as usize;
-        let end = start + byte_count as usize;
+        let byte_count = checked_byte_count(byte_count);
+        let start = self.checked_start(offset, byte_count);
+
+        let target = unsafe { self.memory.as_mut_ptr().add(start) };
+        self.observe(
+            offset,
+            byte_count,
+            AccessKind::Write,
+            Operation::Fill,
+            || unsafe { ptr::write_bytes(target, value, byte_count) },
+        );
+    }
+
+    /// Returns the address of the first nul at or after `address`, or -1 if there is none.
+    pub fn find_null(&self, address: i32) -> i32 {
+        let start = self.checked_start(address, 0);
+        self.scan_null(start, &self.memory[start..])
+            .map_or(-1, |offset| address + offset as i32)
+    }
 
-        debug_assert!(end <= self.memory.len(), "Fill range exceeds memory bounds");
+    /// Returns the address of the first `byte` within `byte_count` bytes of `address`.
+    pub fn find_byte(&self, address: i32, byte_count: usize, byte: u8) -> Option<i32> {
+        search::find_byte(self.search_range(address, byte_count), byte)
+            .map(|offset| address + offset as i32)
+    }
 
-        self.memory[start..end].fill(value);
+    /// Returns the address of the first occurrence of `needle` within `byte_count` bytes of
+    /// `address`.
+    pub fn find_pattern(&self, address: i32, byte_count: usize, needle: &[u8]) -> Option<i32> {
+        search::find_pattern(self.search_range(address, byte_count), needle)
+            .map(|offset| address + offset as i32)
     }
 
-    pub fn find_null(&self, address: i32) -> i32 {
-        let mut offset: usize = address as usize;
-        let len: usize = self.memory.len();
-
-        while offset + VECTOR_SIZE <= len {
-            let chunk = unsafe {
-                let i8_slice = slice::from_raw_parts(
-                    self.memory.as_ptr().add(offset) as *const i8,
-                    VECTOR_SIZE,
-                );
-                Simd::<i8, VECTOR_SIZE>::from_slice(i8_slice)
-            };
-            let mask = chunk.simd_eq(Simd::splat(0));
+    /// Compares two ranges of this memory with `memcmp` semantics.
+    pub fn compare(&self, address_a: i32, address_b: i32, byte_count: usize) -> CmpOrdering {
+        search::compare(
+            self.search_range(address_a, byte_count),
+            self.search_range(address_b, byte_count),
+        )
+    }
 
-            if mask.any() {
-                let first_null: usize = mask.to_bitmask().trailing_zeros() as usize;
-                return (offset + first_null) as i32;
-            }
+    /// Compares memory starting at `address` with a host buffer with `memcmp` semantics.
+    pub fn compare_with(&self, address: i32, other: &[u8]) -> CmpOrdering {
+        search::compare(self.search_range(address, other.len()), other)
+    }
+
+    /// Length of the nul terminated string at `address`, scanning at most `max_len` bytes and
+    /// never past the end of memory.
+    pub fn strnlen(&self, address: i32, max_len: usize) -> usize {
+        let start = self.checked_start(address, 0);
+        let end = start.saturating_add(max_len).min(self.memory.len());
+
+        let haystack = &self.memory[start..end];
+        self.scan_null(start, haystack).unwrap_or(haystack.len())
+    }
 
-            offset += VECTOR_SIZE;
+    /// Finds the first nul of `haystack`, which starts at `start`. Scanning stops at the first
+    /// page that can't be read and traps there unless a nul came before it.
+    fn scan_null(&self, start: usize, haystack: &[u8]) -> Option<usize> {
+        let readable = if self.protections.is_active() {
+            self.protections.readable_len(start, haystack.len())
+        } else {
+            haystack.len()
+        };
+        let offset = search::find_byte(&haystack[..readable], 0);
+        if offset.is_none() && readable < haystack.len() {
+            trap::raise(Trap::Protected);
         }
+        offset
+    }
 
-        while offset < len {
-            if self.memory[offset] == 0 {
-                return offset as i32;
-            }
-            offset += 1;
+    fn search_range(&self, address: i32, byte_count: usize) -> &[u8] {
+        let start = self.checked_start(address, byte_count);
+        let end = start + byte_count;
+
+        if !self.is_accessible(start..end, AccessKind::Read) {
+            trap::raise(Trap::Protected);
         }
 
-        -1
+        &self.memory[start..end]
     }
 
     make_read_writers!(
@@ -182,250 +812,304 @@ impl LinearMemory {
         (@atomic i64, AtomicU32, u32),
     );
 
+    make_array_read_writers!(i32, i64, f32, f64);
+
     pub fn read_bytes(&self, address: i32, byte_count: usize) -> &[u8] {
-        let start = address as usize;
+        let start = self.checked_start(address, byte_count);
         let end = start + byte_count;
 
-        debug_assert!(end <= self.memory.len(), "Read range exceeds memory bounds");
-
+        self.observe(
+            address,
+            byte_count,
+            AccessKind::Read,
+            Operation::ReadBytes,
+            || {},
+        );
         &self.memory[start..end]
     }
 
     pub fn write_bytes(&mut self, address: i32, bytearray: &[u8]) {
-        let start = address as usize;
-        let end = start + bytearray.len();
-
-        debug_assert!(
-            end <= self.memory.len(),
-            "The provided byte array exceeds the memory bounds"
+        let start = self.checked_start(address, bytearray.len());
+
+        let target = unsafe { self.memory.as_mut_ptr().add(start) };
+        self.observe(
+            address,
+            bytearray.len(),
+            AccessKind::Write,
+            Operation::WriteBytes,
+            || unsafe { ptr::copy_nonoverlapping(bytearray.as_ptr(), target, bytearray.len()) },
         );
-
-        self.memory[start..end].copy_from_slice(bytearray);
     }
 
     pub fn atomic_rmw_add_i32(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI32;
-        unsafe { (*aligned_ptr).fetch_add(value, Ordering::SeqCst) }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI32| {
+            atomic.fetch_add(value, Ordering::SeqCst)
+        })
     }
 
     pub fn atomic_rmw_and_i32(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI32;
-        unsafe { (*aligned_ptr).fetch_and(value, Ordering::SeqCst) }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI32| {
+            atomic.fetch_and(value, Ordering::SeqCst)
+        })
     }
 
     pub fn atomic_rmw_sub_i32(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI32;
-        unsafe { (*aligned_ptr).fetch_sub(value, Ordering::SeqCst) }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI32| {
+            atomic.fetch_sub(value, Ordering::SeqCst)
+        })
     }
 
     pub fn atomic_rmw_or_i32(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI32;
-        unsafe { (*aligned_ptr).fetch_or(value, Ordering::SeqCst) }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI32| {
+            atomic.fetch_or(value, Ordering::SeqCst)
+        })
     }
 
     pub fn atomic_rmw_xor_i32(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI32;
-        unsafe { (*aligned_ptr).fetch_xor(value, Ordering::SeqCst) }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI32| {
+            atomic.fetch_xor(value, Ordering::SeqCst)
+        })
     }
 
     pub fn atomic_rmw_exchange_i32(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI32;
-        unsafe { (*aligned_ptr).swap(value, Ordering::SeqCst) }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI32| {
+            atomic.swap(value, Ordering::SeqCst)
+        })
     }
 
     pub fn atomic_rmw_add_i32_to_i8(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI8;
-        unsafe { (*aligned_ptr).fetch_add(value as i8, Ordering::SeqCst) as i32 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI8| {
+            atomic.fetch_add(value as i8, Ordering::SeqCst) as i32
+        })
     }
 
     pub fn atomic_rmw_and_i32_to_i8(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI8;
-        unsafe { (*aligned_ptr).fetch_and(value as i8, Ordering::SeqCst) as i32 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI8| {
+            atomic.fetch_and(value as i8, Ordering::SeqCst) as i32
+        })
     }
 
     pub fn atomic_rmw_sub_i32_to_i8(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI8;
-        unsafe { (*aligned_ptr).fetch_sub(value as i8, Ordering::SeqCst) as i32 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI8| {
+            atomic.fetch_sub(value as i8, Ordering::SeqCst) as i32
+        })
     }
 
     pub fn atomic_rmw_or_i32_to_i8(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI8;
-        unsafe { (*aligned_ptr).fetch_or(value as i8, Ordering::SeqCst) as i32 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI8| {
+            atomic.fetch_or(value as i8, Ordering::SeqCst) as i32
+        })
     }
 
     pub fn atomic_rmw_xor_i32_to_i8(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI8;
-        unsafe { (*aligned_ptr).fetch_xor(value as i8, Ordering::SeqCst) as i32 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI8| {
+            atomic.fetch_xor(value as i8, Ordering::SeqCst) as i32
+        })
     }
 
     pub fn atomic_rmw_exchange_i32_to_i8(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI8;
-        unsafe { (*aligned_ptr).swap(value as i8, Ordering::SeqCst) as i32 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI8| {
+            atomic.swap(value as i8, Ordering::SeqCst) as i32
+        })
     }
 
     pub fn atomic_rmw_add_i32_to_i16(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI16;
-        unsafe { (*aligned_ptr).fetch_add(value as i16, Ordering::SeqCst) as i32 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI16| {
+            atomic.fetch_add(value as i16, Ordering::SeqCst) as i32
+        })
     }
 
     pub fn atomic_rmw_and_i32_to_i16(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI16;
-        unsafe { (*aligned_ptr).fetch_and(value as i16, Ordering::SeqCst) as i32 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI16| {
+            atomic.fetch_and(value as i16, Ordering::SeqCst) as i32
+        })
     }
 
     pub fn atomic_rmw_sub_i32_to_i16(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI16;
-        unsafe { (*aligned_ptr).fetch_sub(value as i16, Ordering::SeqCst) as i32 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI16| {
+            atomic.fetch_sub(value as i16, Ordering::SeqCst) as i32
+        })
     }
 
     pub fn atomic_rmw_or_i32_to_i16(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI16;
-        unsafe { (*aligned_ptr).fetch_or(value as i16, Ordering::SeqCst) as i32 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI16| {
+            atomic.fetch_or(value as i16, Ordering::SeqCst) as i32
+        })
     }
 
     pub fn atomic_rmw_xor_i32_to_i16(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI16;
-        unsafe { (*aligned_ptr).fetch_xor(value as i16, Ordering::SeqCst) as i32 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI16| {
+            atomic.fetch_xor(value as i16, Ordering::SeqCst) as i32
+        })
     }
 
     pub fn atomic_rmw_exchange_i32_to_i16(&self, address: i32, value: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI16;
-        unsafe { (*aligned_ptr).swap(value as i16, Ordering::SeqCst) as i32 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI16| {
+            atomic.swap(value as i16, Ordering::SeqCst) as i32
+        })
     }
 
     pub fn atomic_rmw_add_i64(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI64;
-        unsafe { (*aligned_ptr).fetch_add(value, Ordering::SeqCst) }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI64| {
+            atomic.fetch_add(value, Ordering::SeqCst)
+        })
     }
 
     pub fn atomic_rmw_and_i64(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI64;
-        unsafe { (*aligned_ptr).fetch_and(value, Ordering::SeqCst) }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI64| {
+            atomic.fetch_and(value, Ordering::SeqCst)
+        })
     }
 
     pub fn atomic_rmw_sub_i64(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI64;
-        unsafe { (*aligned_ptr).fetch_sub(value, Ordering::SeqCst) }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI64| {
+            atomic.fetch_sub(value, Ordering::SeqCst)
+        })
     }
 
     pub fn atomic_rmw_or_i64(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI64;
-        unsafe { (*aligned_ptr).fetch_or(value, Ordering::SeqCst) }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI64| {
+            atomic.fetch_or(value, Ordering::SeqCst)
+        })
     }
 
     pub fn atomic_rmw_xor_i64(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI64;
-        unsafe { (*aligned_ptr).fetch_xor(value, Ordering::SeqCst) }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI64| {
+            atomic.fetch_xor(value, Ordering::SeqCst)
+        })
     }
 
     pub fn atomic_rmw_exchange_i64(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI64;
-        unsafe { (*aligned_ptr).swap(value, Ordering::SeqCst) }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI64| {
+            atomic.swap(value, Ordering::SeqCst)
+        })
     }
 
     pub fn atomic_rmw_add_i64_to_i8(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI8;
-        unsafe { (*aligned_ptr).fetch_add(value as i8, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI8| {
+            atomic.fetch_add(value as i8, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_and_i64_to_i8(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI8;
-        unsafe { (*aligned_ptr).fetch_and(value as i8, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI8| {
+            atomic.fetch_and(value as i8, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_sub_i64_to_i8(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI8;
-        unsafe { (*aligned_ptr).fetch_sub(value as i8, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI8| {
+            atomic.fetch_sub(value as i8, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_or_i64_to_i8(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI8;
-        unsafe { (*aligned_ptr).fetch_or(value as i8, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI8| {
+            atomic.fetch_or(value as i8, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_xor_i64_to_i8(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI8;
-        unsafe { (*aligned_ptr).fetch_xor(value as i8, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI8| {
+            atomic.fetch_xor(value as i8, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_exchange_i64_to_i8(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI8;
-        unsafe { (*aligned_ptr).swap(value as i8, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI8| {
+            atomic.swap(value as i8, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_add_i64_to_i16(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI16;
-        unsafe { (*aligned_ptr).fetch_add(value as i16, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI16| {
+            atomic.fetch_add(value as i16, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_and_i64_to_i16(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI16;
-        unsafe { (*aligned_ptr).fetch_and(value as i16, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI16| {
+            atomic.fetch_and(value as i16, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_sub_i64_to_i16(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI16;
-        unsafe { (*aligned_ptr).fetch_sub(value as i16, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI16| {
+            atomic.fetch_sub(value as i16, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_or_i64_to_i16(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI16;
-        unsafe { (*aligned_ptr).fetch_or(value as i16, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI16| {
+            atomic.fetch_or(value as i16, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_xor_i64_to_i16(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI16;
-        unsafe { (*aligned_ptr).fetch_xor(value as i16, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI16| {
+            atomic.fetch_xor(value as i16, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_exchange_i64_to_i16(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI16;
-        unsafe { (*aligned_ptr).swap(value as i16, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI16| {
+            atomic.swap(value as i16, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_add_i64_to_i32(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI32;
-        unsafe { (*aligned_ptr).fetch_add(value as i32, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI32| {
+            atomic.fetch_add(value as i32, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_and_i64_to_i32(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI32;
-        unsafe { (*aligned_ptr).fetch_and(value as i32, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI32| {
+            atomic.fetch_and(value as i32, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_sub_i64_to_i32(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI32;
-        unsafe { (*aligned_ptr).fetch_sub(value as i32, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI32| {
+            atomic.fetch_sub(value as i32, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_or_i64_to_i32(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI32;
-        unsafe { (*aligned_ptr).fetch_or(value as i32, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI32| {
+            atomic.fetch_or(value as i32, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_xor_i64_to_i32(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI32;
-        unsafe { (*aligned_ptr).fetch_xor(value as i32, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI32| {
+            atomic.fetch_xor(value as i32, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_rmw_exchange_i64_to_i32(&self, address: i32, value: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI32;
-        unsafe { (*aligned_ptr).swap(value as i32, Ordering::SeqCst) as i64 }
+        self.atomic(address, Operation::AtomicRmw, |atomic: &AtomicI32| {
+            atomic.swap(value as i32, Ordering::SeqCst) as i64
+        })
     }
 
     pub fn atomic_compare_exchange_i32(&self, address: i32, current: i32, new: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI32;
-        unsafe {
-            (*aligned_ptr)
-                .compare_exchange(current, new, Ordering::SeqCst, Ordering::SeqCst)
-                .unwrap_or_else(|value| value)
-        }
+        self.atomic(
+            address,
+            Operation::AtomicCompareExchange,
+            |atomic: &AtomicI32| {
+                atomic
+                    .compare_exchange(current, new, Ordering::SeqCst, Ordering::SeqCst)
+                    .unwrap_or_else(|value| value)
+            },
+        )
     }
 
     pub fn atomic_compare_exchange_i32_to_i8(&self, address: i32, current: i32, new: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI8;
-        unsafe {
-            match (*aligned_ptr).compare_exchange(
+        self.atomic(
+            address,
+            Operation::AtomicCompareExchange,
+            |atomic: &AtomicI8| match atomic.compare_exchange(
                 current as i8,
                 new as i8,
                 Ordering::SeqCst,
@@ -433,14 +1117,15 @@ impl LinearMemory {
             ) {
                 Ok(value) => value as i32,
                 Err(value) => value as i32,
-            }
-        }
+            },
+        )
     }
 
     pub fn atomic_compare_exchange_i32_to_i16(&self, address: i32, current: i32, new: i32) -> i32 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI16;
-        unsafe {
-            match (*aligned_ptr).compare_exchange(
+        self.atomic(
+            address,
+            Operation::AtomicCompareExchange,
+            |atomic: &AtomicI16| match atomic.compare_exchange(
                 current as i16,
                 new as i16,
                 Ordering::SeqCst,
@@ -448,23 +1133,27 @@ impl LinearMemory {
             ) {
                 Ok(value) => value as i32,
                 Err(value) => value as i32,
-            }
-        }
+            },
+        )
     }
 
     pub fn atomic_compare_exchange_i64(&self, address: i32, current: i64, new: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI64;
-        unsafe {
-            (*aligned_ptr)
-                .compare_exchange(current, new, Ordering::SeqCst, Ordering::SeqCst)
-                .unwrap_or_else(|value| value)
-        }
+        self.atomic(
+            address,
+            Operation::AtomicCompareExchange,
+            |atomic: &AtomicI64| {
+                atomic
+                    .compare_exchange(current, new, Ordering::SeqCst, Ordering::SeqCst)
+                    .unwrap_or_else(|value| value)
+            },
+        )
     }
 
     pub fn atomic_compare_exchange_i64_to_i8(&self, address: i32, current: i64, new: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI8;
-        unsafe {
-            match (*aligned_ptr).compare_exchange(
+        self.atomic(
+            address,
+            Operation::AtomicCompareExchange,
+            |atomic: &AtomicI8| match atomic.compare_exchange(
                 current as i8,
                 new as i8,
                 Ordering::SeqCst,
@@ -472,14 +1161,15 @@ impl LinearMemory {
             ) {
                 Ok(value) => value as i64,
                 Err(value) => value as i64,
-            }
-        }
+            },
+        )
     }
 
     pub fn atomic_compare_exchange_i64_to_i16(&self, address: i32, current: i64, new: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI16;
-        unsafe {
-            match (*aligned_ptr).compare_exchange(
+        self.atomic(
+            address,
+            Operation::AtomicCompareExchange,
+            |atomic: &AtomicI16| match atomic.compare_exchange(
                 current as i16,
                 new as i16,
                 Ordering::SeqCst,
@@ -487,14 +1177,15 @@ impl LinearMemory {
             ) {
                 Ok(value) => value as i64,
                 Err(value) => value as i64,
-            }
-        }
+            },
+        )
     }
 
     pub fn atomic_compare_exchange_i64_to_i32(&self, address: i32, current: i64, new: i64) -> i64 {
-        let aligned_ptr = self.memory[address as usize..].as_ptr() as *const AtomicI32;
-        unsafe {
-            match (*aligned_ptr).compare_exchange(
+        self.atomic(
+            address,
+            Operation::AtomicCompareExchange,
+            |atomic: &AtomicI32| match atomic.compare_exchange(
                 current as i32,
                 new as i32,
                 Ordering::SeqCst,
@@ -502,95 +1193,171 @@ impl LinearMemory {
             ) {
                 Ok(value) => value as i64,
                 Err(value) => value as i64,
-            }
-        }
+            },
+        )
     }
 
     pub fn atomic_fence(&self) {
-        std::sync::atomic::fence(Ordering::SeqCst);
+        #[cfg(feature = "threads")]
+        if let Some(detector) = &self.race_detector {
+            detector.fence();
+        }
+        core::sync::atomic::fence(Ordering::SeqCst);
     }
 
-    #[inline(always)]
+    #[cfg(feature = "threads")]
     fn wait(&self, addr: i32, timeout_nanos: i64) -> i32 {
-        let wait_entry = Arc::new(WaitEntry {
-            condvar: Condvar::new(),
-            mutex: Mutex::new(()),
-        });
-
-        let mut guard = wait_entry.mutex.lock();
-        let queue = self
+        let deadline = (timeout_nanos >= 0)
+            .then(|| Instant::now() + Duration::from_nanos(timeout_nanos as u64));
+        let result = self
             .wait_queues
-            .entry(addr)
-            .or_insert_with(|| Arc::new(ConcurrentQueue::unbounded()))
-            .clone();
-
-        queue.push(wait_entry.clone()).unwrap();
-
-        let timed_out = if timeout_nanos >= 0 {
-            let timeout = Duration::from_nanos(timeout_nanos as u64);
-            let instant = Instant::now() + timeout;
-            wait_entry
-                .condvar
-                .wait_until(&mut guard, instant)
-                .timed_out()
-        } else {
-            wait_entry.condvar.wait(&mut guard);
-            false
-        };
+            .wait(addr, deadline, self.scheduler.as_deref());
+        if result == WAIT_OK {
+            self.acquire_notify(addr);
+        }
+        result
+    }
 
-        if timed_out {
-            2
-        } else {
-            0
+    /// Orders a woken waiter after the `notify` which woke it.
+    #[cfg(feature = "threads")]
+    pub(crate) fn acquire_notify(&self, addr: i32) {
+        if let Some(detector) = &self.race_detector {
+            detector.acquire(SyncKey::Address(addr));
         }
     }
 
+    #[cfg(feature = "threads")]
     pub fn wait_i32(&self, addr: i32, expected: i32, timeout_nanos: i64) -> i32 {
-        let atomic = unsafe { &*(self.memory.as_ptr().add(addr as usize) as *const AtomicI32) };
-
-        if atomic.load(Ordering::SeqCst) != expected {
-            return 1;
+        if self.wait_queues.is_terminated() {
+            return WAIT_TERMINATED;
+        }
+        if self.atomic_load_for_wait_i32(addr) != expected {
+            return WAIT_NOT_EQUAL;
         }
 
         self.wait(addr, timeout_nanos)
     }
 
+    #[cfg(feature = "threads")]
     pub fn wait_i64(&self, addr: i32, expected: i64, timeout_nanos: i64) -> i32 {
-        let atomic = unsafe { &*(self.memory.as_ptr().add(addr as usize) as *const AtomicI64) };
-
-        if atomic.load(Ordering::SeqCst) != expected {
-            return 1;
+        if self.wait_queues.is_terminated() {
+            return WAIT_TERMINATED;
+        }
+        if self.atomic_load_for_wait_i64(addr) != expected {
+            return WAIT_NOT_EQUAL;
         }
 
         self.wait(addr, timeout_nanos)
     }
 
+    #[cfg(feature = "threads")]
+    /// Like `wait_i32` without blocking the thread, the returned future resolves once notified.
+    /// It joins the same FIFO queue as blocking waiters and cancels the wait when dropped.
+    pub fn wait_async_i32(&self, addr: i32, expected: i32) -> WaitAsync<'_> {
+        let entry = (self.wait_queues.is_terminated()
+            || self.atomic_load_for_wait_i32(addr) == expected)
+            .then(|| self.wait_queues.wait_async(addr));
+        WaitAsync::new(self, addr, entry)
+    }
+
+    #[cfg(feature = "threads")]
+    pub fn wait_async_i64(&self, addr: i32, expected: i64) -> WaitAsync<'_> {
+        let entry = (self.wait_queues.is_terminated()
+            || self.atomic_load_for_wait_i64(addr) == expected)
+            .then(|| self.wait_queues.wait_async(addr));
+        WaitAsync::new(self, addr, entry)
+    }
+
+    #[cfg(feature = "threads")]
+    /// Queues `callback` to run with the wait result on the notifying thread. Returns None without
+    /// queueing it if `addr` does not hold `expected`, once waiters are terminated the callback
+    /// runs at once.
+    pub fn wait_callback_i32(
+        &self,
+        addr: i32,
+        expected: i32,
+        callback: impl FnOnce(i32) + Send + 'static,
+    ) -> Option<WaitHandle> {
+        (self.wait_queues.is_terminated() || self.atomic_load_for_wait_i32(addr) == expected)
+            .then(|| self.wait_queues.wait_callback(addr, Box::new(callback)))
+    }
+
+    #[cfg(feature = "threads")]
+    pub fn wait_callback_i64(
+        &self,
+        addr: i32,
+        expected: i64,
+        callback: impl FnOnce(i32) + Send + 'static,
+    ) -> Option<WaitHandle> {
+        (self.wait_queues.is_terminated() || self.atomic_load_for_wait_i64(addr) == expected)
+            .then(|| self.wait_queues.wait_callback(addr, Box::new(callback)))
+    }
+
+    #[cfg(feature = "threads")]
+    fn atomic_load_for_wait_i32(&self, addr: i32) -> i32 {
+        self.atomic(addr, Operation::AtomicLoad, |atomic: &AtomicI32| {
+            atomic.load(Ordering::SeqCst)
+        })
+    }
+
+    #[cfg(feature = "threads")]
+    fn atomic_load_for_wait_i64(&self, addr: i32) -> i64 {
+        self.atomic(addr, Operation::AtomicLoad, |atomic: &AtomicI64| {
+            atomic.load(Ordering::SeqCst)
+        })
+    }
+
+    #[cfg(feature = "threads")]
+    /// Wakes up to `count` waiters on `addr`, blocking and async alike, in the order they began
+    /// waiting.
     pub fn notify(&self, addr: i32, count: i32) -> i32 {
-        let mut woken_count = 0;
+        if let Err(trap) = self.atomic_start(addr, 4) {
+            trap::raise(trap);
+        }
+        if let Some(detector) = &self.race_detector {
+            detector.release(SyncKey::Address(addr));
+        }
 
-        let Some(queue) = self.wait_queues.get(&addr) else {
-            return woken_count;
-        };
+        if let Some(scheduler) = &self.scheduler {
+            scheduler.yield_now();
+        }
+        self.wait_queues
+            .notify(addr, count.max(0) as u32, self.scheduler.as_deref()) as i32
+    }
 
-        for _ in 0..count {
-            if let Ok(entry) = queue.pop() {
-                let guard = entry.mutex.lock();
-                let woken = entry.condvar.notify_one();
-                woken_count += woken as i32;
-                drop(guard);
-            } else {
-                break;
-            }
+    #[cfg(feature = "threads")]
+    /// Wakes every waiter with `WAIT_TERMINATED`, and makes every later wait return it at once.
+    /// Runs on drop so threads parked in a wait never outlive the memory.
+    pub fn terminate_waiters(&self) {
+        self.wait_queues.terminate();
+    }
+
+    /// Without threads nothing can be waiting.
+    #[cfg(not(feature = "threads"))]
+    pub fn notify(&self, addr: i32, _count: i32) -> i32 {
+        if let Err(trap) = self.atomic_start(addr, 4) {
+            trap::raise(trap);
         }
+        0
+    }
+}
 
-        woken_count
+impl Drop for LinearMemory {
+    fn drop(&mut self) {
+        #[cfg(feature = "threads")]
+        self.terminate_waiters();
+        if let Some(limiter) = &self.limiter {
+            limiter.memory_released(self.memory.len());
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "threads")]
     use std::sync::{Arc, Barrier};
+    #[cfg(feature = "threads")]
     use std::thread;
 
     #[test]
@@ -617,6 +1384,78 @@ mod tests {
         assert_eq!(memory.memory[new_size - 1], 0);
     }
 
+    #[test]
+    fn test_descriptor() {
+        let mut memory = LinearMemory::new(1);
+        let descriptor = memory.descriptor();
+
+        assert_eq!(descriptor.byte_length, PAGE_SIZE as usize);
+        assert_eq!(descriptor.generation, 0);
+        unsafe { descriptor.base.add(8).write(7) };
+        assert_eq!(memory.read_i32(8), 7);
+
+        assert!(memory.grow(1));
+        let descriptor = memory.descriptor();
+
+        assert_eq!(memory.generation(), 1);
+        assert_eq!(descriptor.generation, 1);
+        assert_eq!(descriptor.byte_length, 2 * PAGE_SIZE as usize);
+        assert_eq!(unsafe { descriptor.base.add(8).read() }, 7);
+    }
+
+    #[test]
+    fn test_try_new_and_try_grow() {
+        let mut memory = LinearMemory::try_new(1).unwrap();
+
+        assert!(matches!(
+            memory.try_grow(u32::MAX),
+            Err(MemoryError::SizeOverflow) | Err(MemoryError::MapFailed(_))
+        ));
+        assert_eq!(memory.size(), PAGE_SIZE as usize);
+        assert_eq!(memory.generation(), 0);
+        assert!(memory.try_grow(1).is_ok());
+        assert_eq!(memory.size(), 2 * PAGE_SIZE as usize);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_grow_listener() {
+        use std::sync::Mutex;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut memory = LinearMemory::new(1);
+        let old_base = memory.descriptor().base as usize;
+        let id = memory.add_grow_listener({
+            let events = events.clone();
+            move |event: &GrowEvent| {
+                events.lock().unwrap().push((
+                    event.old_base as usize,
+                    event.old_size,
+                    event.new_base as usize,
+                    event.new_size,
+                ))
+            }
+        });
+
+        assert!(memory.grow(2));
+        assert!(!memory.grow(u32::MAX));
+        let new_base = memory.descriptor().base as usize;
+        assert_eq!(
+            *events.lock().unwrap(),
+            [(
+                old_base,
+                PAGE_SIZE as usize,
+                new_base,
+                3 * PAGE_SIZE as usize
+            )]
+        );
+
+        assert!(memory.remove_grow_listener(id));
+        assert!(!memory.remove_grow_listener(id));
+        assert!(memory.grow(1));
+        assert_eq!(events.lock().unwrap().len(), 1);
+    }
+
     #[test]
     fn test_copy() {
         let mut src_memory = LinearMemory::new(1);
@@ -637,6 +1476,127 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_out_of_bounds_access_traps() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        fn trap_of<R>(access: impl FnOnce() -> R) -> Option<Trap> {
+            panic::catch_unwind(AssertUnwindSafe(access))
+                .err()
+                .and_then(|payload| payload.downcast::<Trap>().ok())
+                .map(|trap| *trap)
+        }
+
+        let mut memory = LinearMemory::new(1);
+        let mut other = LinearMemory::new(1);
+        let size = memory.size() as i32;
+
+        assert_eq!(
+            trap_of(|| memory.read_i32(size - 2)),
+            Some(Trap::OutOfBounds)
+        );
+        assert_eq!(trap_of(|| memory.read_i64(-1)), Some(Trap::OutOfBounds));
+        assert_eq!(
+            trap_of(|| memory.write_i32_to_u16(size - 1, 1)),
+            Some(Trap::OutOfBounds)
+        );
+        assert_eq!(
+            trap_of(|| memory.atomic_rmw_add_i32(size, 1)),
+            Some(Trap::OutOfBounds)
+        );
+        assert_eq!(
+            trap_of(|| memory.copy(size - 2, &mut other, 0, 4)),
+            Some(Trap::OutOfBounds)
+        );
+        assert_eq!(
+            trap_of(|| memory.copy(0, &mut other, 0, -1)),
+            Some(Trap::OutOfBounds)
+        );
+        assert_eq!(other.read_i32(0), 0);
+    }
+
+    #[test]
+    fn test_unaligned_atomics_trap() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let memory = LinearMemory::new(1);
+        let unaligned = |access: &dyn Fn()| {
+            let trapped = panic::catch_unwind(AssertUnwindSafe(access));
+            *trapped.unwrap_err().downcast::<Trap>().unwrap() == Trap::UnalignedAtomic
+        };
+
+        assert!(unaligned(&|| {
+            memory.atomic_rmw_add_i32(2, 1);
+        }));
+        assert!(unaligned(&|| {
+            let _ = memory.atomic_read_i64(4);
+        }));
+        assert!(unaligned(&|| {
+            memory.atomic_compare_exchange_i64_to_i16(1, 0, 1);
+        }));
+        assert!(unaligned(&|| {
+            memory.notify(1, 1);
+        }));
+        #[cfg(feature = "threads")]
+        assert!(unaligned(&|| {
+            memory.wait_i32(6, 0, 0);
+        }));
+        memory.atomic_write_i32_to_u8(3, 1);
+        assert_eq!(memory.atomic_read_i32_from_u8(3), 1);
+    }
+
+    #[test]
+    fn test_copy_within() {
+        let mut memory = LinearMemory::new(1);
+        memory.memory[0..6].copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+
+        memory.copy_within(0, 2, 4);
+        assert_eq!(&memory.memory[0..6], &[1, 2, 1, 2, 3, 4]);
+
+        memory.copy_within(2, 0, 4);
+        assert_eq!(&memory.memory[0..6], &[1, 2, 3, 4, 3, 4]);
+    }
+
+    #[test]
+    fn test_bulk_operations_out_of_bounds_trap() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        fn traps_out_of_bounds(access: impl FnOnce()) -> bool {
+            let trapped = panic::catch_unwind(AssertUnwindSafe(access));
+            trapped
+                .err()
+                .and_then(|payload| payload.downcast::<Trap>().ok())
+                .is_some_and(|trap| *trap == Trap::OutOfBounds)
+        }
+
+        let mut memory = LinearMemory::new(1);
+        let size = memory.size() as i32;
+        memory.memory[0] = 1;
+
+        assert!(traps_out_of_bounds(|| memory.copy_within(0, size - 2, 4)));
+        assert!(traps_out_of_bounds(|| memory.copy_within(-1, 0, 4)));
+        assert!(traps_out_of_bounds(|| memory.copy_within(size - 2, 0, 4)));
+        assert!(traps_out_of_bounds(|| memory.copy_within(0, 4, -1)));
+        assert!(traps_out_of_bounds(|| memory.fill(size - 2, 4, 1)));
+        assert!(traps_out_of_bounds(|| memory.fill(0, -1, 1)));
+        assert!(traps_out_of_bounds(|| memory.write_bytes(size - 1, &[1, 1])));
+        assert!(traps_out_of_bounds(|| {
+            memory.read_bytes(-1, 1);
+        }));
+        assert!(traps_out_of_bounds(|| {
+            memory.find_null(size + 1);
+        }));
+        assert!(traps_out_of_bounds(|| {
+            memory.strnlen(-1, 4);
+        }));
+        assert!(traps_out_of_bounds(|| {
+            memory.find_byte(size - 2, 4, 0);
+        }));
+        assert_eq!(memory.memory[size as usize - 2], 0);
+        assert_eq!(memory.memory[0], 1);
+        assert_eq!(memory.find_null(size), -1);
+    }
+
     #[test]
     fn test_fill() {
         let mut linear_memory = LinearMemory::new(1);
@@ -669,6 +1629,61 @@ mod tests {
         assert_eq!(null_offset, (offset + bytes.len()) as i32);
     }
 
+    #[test]
+    fn test_find_byte() {
+        let mut memory = LinearMemory::new(1);
+
+        memory.write_bytes(40, b"abcdefghijklmnopqrstuvwxyz");
+
+        assert_eq!(memory.find_byte(40, 26, b'x'), Some(63));
+        assert_eq!(memory.find_byte(40, 23, b'x'), None);
+    }
+
+    #[test]
+    fn test_find_pattern() {
+        let mut memory = LinearMemory::new(1);
+
+        memory.write_bytes(40, b"abcdefghijklmnopqrstuvwxyz");
+
+        assert_eq!(memory.find_pattern(40, 26, b"uvw"), Some(60));
+        assert_eq!(memory.find_pattern(40, 22, b"uvw"), None);
+    }
+
+    #[test]
+    fn test_compare() {
+        let mut memory = LinearMemory::new(1);
+
+        memory.write_bytes(0, b"hello world");
+        memory.write_bytes(100, b"hello there");
+
+        assert_eq!(memory.compare(0, 100, 6), CmpOrdering::Equal);
+        assert_eq!(memory.compare(0, 100, 11), CmpOrdering::Greater);
+        assert_eq!(memory.compare(100, 0, 11), CmpOrdering::Less);
+    }
+
+    #[test]
+    fn test_compare_with() {
+        let mut memory = LinearMemory::new(1);
+
+        memory.write_bytes(8, b"linmem");
+
+        assert_eq!(memory.compare_with(8, b"linmem"), CmpOrdering::Equal);
+        assert_eq!(memory.compare_with(8, b"linux!"), CmpOrdering::Less);
+    }
+
+    #[test]
+    fn test_strnlen() {
+        let mut memory = LinearMemory::new(1);
+        let size = memory.size();
+
+        memory.write_bytes(0, b"hello\0");
+        memory.fill(size as i32 - 4, 4, b'a');
+
+        assert_eq!(memory.strnlen(0, 100), 5);
+        assert_eq!(memory.strnlen(0, 3), 3);
+        assert_eq!(memory.strnlen(size as i32 - 4, 100), 4);
+    }
+
     #[test]
     fn test_i32_rw() {
         let mut memory = LinearMemory::new(1);
@@ -895,6 +1910,72 @@ mod tests {
         assert!(read_value.is_nan());
     }
 
+    #[test]
+    fn test_i32_array_rw() {
+        let mut memory = LinearMemory::new(1);
+
+        let values = [1, -2, i32::MAX, i32::MIN];
+        let mut read_values = [0; 4];
+
+        assert!(memory.write_i32_array(3, &values));
+        assert!(memory.read_i32_array(3, &mut read_values));
+
+        assert_eq!(values, read_values);
+        assert_eq!(memory.read_i32(7), -2);
+    }
+
+    #[test]
+    fn test_i64_array_rw() {
+        let mut memory = LinearMemory::new(1);
+
+        let values = [i64::MAX, 0, -1];
+        let mut read_values = [1; 3];
+
+        assert!(memory.write_i64_array(16, &values));
+        assert!(memory.read_i64_array(16, &mut read_values));
+
+        assert_eq!(values, read_values);
+    }
+
+    #[test]
+    fn test_f32_array_rw() {
+        let mut memory = LinearMemory::new(1);
+
+        let values = [1.5f32, f32::INFINITY, -0.0];
+        let mut read_values = [0.0; 3];
+
+        assert!(memory.write_f32_array(1, &values));
+        assert!(memory.read_f32_array(1, &mut read_values));
+
+        assert_eq!(values, read_values);
+        assert_eq!(memory.read_f32(5), f32::INFINITY);
+    }
+
+    #[test]
+    fn test_f64_array_rw() {
+        let mut memory = LinearMemory::new(1);
+
+        let values = [std::f64::consts::PI, f64::MIN_POSITIVE];
+        let mut read_values = [0.0; 2];
+
+        assert!(memory.write_f64_array(64, &values));
+        assert!(memory.read_f64_array(64, &mut read_values));
+
+        assert_eq!(values, read_values);
+        assert_eq!(memory.read_f64(72), f64::MIN_POSITIVE);
+    }
+
+    #[test]
+    fn test_array_out_of_bounds() {
+        let mut memory = LinearMemory::new(1);
+        let last = memory.size() as i32 - 8;
+
+        assert!(memory.write_i32_array(last, &[1, 2]));
+        assert!(!memory.write_i32_array(last, &[1, 2, 3]));
+        assert!(!memory.read_f64_array(last, &mut [0.0; 2]));
+        assert!(!memory.read_i64_array(-8, &mut [0; 1]));
+    }
+
     #[test]
     fn test_read_bytes() {
         let mut linear_memory = LinearMemory::new(1);
@@ -2064,6 +3145,7 @@ mod tests {
         assert_eq!(final_value, new_value);
     }
 
+    #[cfg(feature = "threads")]
     #[test]
     fn test_wait32_with_notify() {
         let mut memory = Arc::new(LinearMemory::new(1));
@@ -2096,6 +3178,97 @@ mod tests {
         assert_eq!(result, 0);
     }
 
+    #[cfg(feature = "threads")]
+    #[test]
+    fn test_notify_wakes_in_fifo_order() {
+        use std::sync::Mutex;
+
+        let memory = Arc::new(LinearMemory::new(1));
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let first = memory
+            .wait_callback_i32(0, 0, {
+                let order = order.clone();
+                move |result| order.lock().unwrap().push(("callback", result))
+            })
+            .unwrap();
+        let blocking = thread::spawn({
+            let memory = memory.clone();
+            let order = order.clone();
+            move || {
+                let result = memory.wait_i32(0, 0, -1);
+                order.lock().unwrap().push(("blocking", result));
+            }
+        });
+        while memory.wait_queues.len(0) < 2 {
+            thread::yield_now();
+        }
+        let cancelled = memory
+            .wait_callback_i32(0, 0, |_| panic!("Cancelled wait completed"))
+            .unwrap();
+        assert!(cancelled.cancel());
+
+        assert_eq!(memory.notify(0, 1), 1);
+        assert_eq!(*order.lock().unwrap(), [("callback", WAIT_OK)]);
+        assert!(!first.cancel());
+
+        assert_eq!(memory.notify(0, 2), 1);
+        blocking.join().unwrap();
+        assert_eq!(
+            *order.lock().unwrap(),
+            [("callback", WAIT_OK), ("blocking", WAIT_OK)]
+        );
+    }
+
+    #[cfg(feature = "threads")]
+    #[test]
+    fn test_terminate_waiters() {
+        use crate::wait::WAIT_TERMINATED;
+        use std::sync::atomic::AtomicI32;
+
+        let memory = Arc::new(LinearMemory::new(1));
+        let blocking = thread::spawn({
+            let memory = memory.clone();
+            move || memory.wait_i64(8, 0, -1)
+        });
+        while memory.wait_queues.len(8) < 1 {
+            thread::yield_now();
+        }
+
+        memory.terminate_waiters();
+        assert_eq!(blocking.join().unwrap(), WAIT_TERMINATED);
+        assert_eq!(memory.wait_i32(8, 0, -1), WAIT_TERMINATED);
+        assert_eq!(memory.wait_i32(8, 1, -1), WAIT_TERMINATED);
+        assert_eq!(memory.notify(8, 1), 0);
+
+        // Dropping the memory terminates waiters which are still queued
+        let result = Arc::new(AtomicI32::new(-1));
+        let memory = LinearMemory::new(1);
+        let _handle = memory.wait_callback_i32(0, 0, {
+            let result = result.clone();
+            move |value| result.store(value, Ordering::SeqCst)
+        });
+        drop(memory);
+        assert_eq!(result.load(Ordering::SeqCst), WAIT_TERMINATED);
+    }
+
+    #[cfg(feature = "threads")]
+    #[test]
+    fn test_resolved_waits_are_pruned() {
+        use crate::wait::WAIT_TIMED_OUT;
+
+        let memory = LinearMemory::new(1);
+
+        for _ in 0..100 {
+            assert_eq!(memory.wait_i32(16, 0, 0), WAIT_TIMED_OUT);
+            let handle = memory.wait_callback_i32(16, 0, |_| unreachable!()).unwrap();
+            assert!(handle.cancel());
+            drop(memory.wait_async_i32(16, 0));
+        }
+        assert!(memory.wait_queues.len(16) <= 1);
+    }
+
+    #[cfg(feature = "threads")]
     #[test]
     fn test_wait64_with_notify() {
         let mut memory = Arc::new(LinearMemory::new(1));
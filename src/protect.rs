@@ -0,0 +1,212 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::error::MemoryError;
+use crate::memory::{AccessKind, PAGE_SIZE};
+
+const PAGE_BYTES: usize = PAGE_SIZE as usize;
+
+/// Access allowed to a range of pages, matching the memory-control proposal's `memory.protect`.
+/// cbindgen:prefix-with-name
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protection {
+    NoAccess = 0,
+    ReadOnly = 1,
+    ReadWrite = 3,
+}
+
+impl TryFrom<u32> for Protection {
+    type Error = MemoryError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Protection::NoAccess),
+            1 => Ok(Protection::ReadOnly),
+            3 => Ok(Protection::ReadWrite),
+            _ => Err(MemoryError::InvalidArgument),
+        }
+    }
+}
+
+impl Protection {
+    pub fn allows(self, kind: AccessKind) -> bool {
+        (self as u8) & (kind as u8) == kind as u8
+    }
+}
+
+/// Software copy of the page protections, every access made through `LinearMemory` is checked
+/// against it so protected pages trap rather than fault.
+#[derive(Default)]
+pub(crate) struct PageProtections {
+    pages: Vec<Protection>,
+    active: bool,
+}
+
+impl PageProtections {
+    #[inline(always)]
+    pub(crate) fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub(crate) fn resize(&mut self, page_count: usize) {
+        if self.active {
+            self.pages.resize(page_count, Protection::ReadWrite);
+        }
+    }
+
+    pub(crate) fn set(&mut self, page_count: usize, pages: Range<usize>, protection: Protection) {
+        self.pages.resize(page_count, Protection::ReadWrite);
+        self.pages[pages].fill(protection);
+        self.active = self.pages.iter().any(|&page| page != Protection::ReadWrite);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.pages.clear();
+        self.active = false;
+    }
+
+    /// True if every page overlapping the byte range allows `kind`.
+    pub(crate) fn allows(&self, range: Range<usize>, kind: AccessKind) -> bool {
+        if range.is_empty() {
+            return true;
+        }
+        let first = range.start / PAGE_BYTES;
+        let last = (range.end - 1) / PAGE_BYTES;
+        self.pages
+            .iter()
+            .skip(first)
+            .take(last - first + 1)
+            .all(|page| page.allows(kind))
+    }
+
+    /// Bytes from `start` up to the first page which can't be read, at most `max_len`.
+    pub(crate) fn readable_len(&self, start: usize, max_len: usize) -> usize {
+        let first = start / PAGE_BYTES;
+        let blocked = self.pages[first.min(self.pages.len())..]
+            .iter()
+            .position(|page| !page.allows(AccessKind::Read))
+            .map_or(usize::MAX, |offset| (first + offset) * PAGE_BYTES);
+        max_len.min(blocked.saturating_sub(start))
+    }
+
+    /// Runs of consecutive pages which are not read write, as page ranges.
+    pub(crate) fn restricted_runs(&self) -> impl Iterator<Item = (Range<usize>, Protection)> + '_ {
+        let mut page = 0;
+        core::iter::from_fn(move || {
+            while page < self.pages.len() && self.pages[page] == Protection::ReadWrite {
+                page += 1;
+            }
+            let start = page;
+            let protection = *self.pages.get(start)?;
+            while page < self.pages.len() && self.pages[page] == protection {
+                page += 1;
+            }
+            Some((start..page, protection))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::{BatchOp, BatchOpcode, BatchTrap};
+    use crate::memory::LinearMemory;
+    use crate::trap::Trap;
+    use std::panic::{self, AssertUnwindSafe};
+
+    const PAGE: i32 = PAGE_BYTES as i32;
+
+    fn trap_of<R>(access: impl FnOnce() -> R) -> Option<Trap> {
+        panic::catch_unwind(AssertUnwindSafe(access))
+            .err()
+            .and_then(|payload| payload.downcast::<Trap>().ok())
+            .map(|trap| *trap)
+    }
+
+    #[test]
+    fn test_read_only_page() {
+        let mut memory = LinearMemory::new(2);
+        memory.write_i32(PAGE + 8, 7);
+        memory.protect(1..2, Protection::ReadOnly).unwrap();
+
+        assert_eq!(memory.read_i32(PAGE + 8), 7);
+        assert_eq!(
+            trap_of(|| memory.write_i32(PAGE + 8, 1)),
+            Some(Trap::Protected)
+        );
+        assert_eq!(
+            trap_of(|| memory.fill(PAGE - 2, 4, 0)),
+            Some(Trap::Protected)
+        );
+        memory.write_i32(PAGE - 4, 1);
+
+        memory.protect(1..2, Protection::ReadWrite).unwrap();
+        memory.write_i32(PAGE + 8, 9);
+        assert_eq!(memory.read_i32(PAGE + 8), 9);
+    }
+
+    #[test]
+    fn test_no_access_pages() {
+        let mut memory = LinearMemory::new(3);
+        memory.write_bytes(2 * PAGE - 3, b"abc");
+        memory.protect(0..1, Protection::NoAccess).unwrap();
+        memory.protect(2..3, Protection::NoAccess).unwrap();
+
+        assert_eq!(trap_of(|| memory.read_i32(0)), Some(Trap::Protected));
+        assert_eq!(
+            trap_of(|| memory.atomic_rmw_add_i32(16, 1)),
+            Some(Trap::Protected)
+        );
+        assert_eq!(trap_of(|| memory.find_byte(0, 8, 0)), Some(Trap::Protected));
+        assert_eq!(memory.strnlen(2 * PAGE - 3, 2), 2);
+        assert_eq!(
+            trap_of(|| memory.strnlen(2 * PAGE - 3, 8)),
+            Some(Trap::Protected)
+        );
+        assert_eq!(
+            trap_of(|| memory.find_null(2 * PAGE - 3)),
+            Some(Trap::Protected)
+        );
+        assert!(memory.protect(2..4, Protection::ReadOnly).is_err());
+
+        memory.reset().unwrap();
+        assert_eq!(memory.read_i32(0), 0);
+    }
+
+    #[test]
+    fn test_protection_survives_grow() {
+        let mut memory = LinearMemory::new(2);
+        memory.protect(1..2, Protection::ReadOnly).unwrap();
+
+        assert!(memory.grow(2));
+        memory.write_i32(3 * PAGE, 1);
+
+        assert_eq!(trap_of(|| memory.write_i32(PAGE, 1)), Some(Trap::Protected));
+        assert_eq!(memory.read_i32(3 * PAGE), 1);
+    }
+
+    #[test]
+    fn test_batch_traps_on_protected_page() {
+        let mut memory = LinearMemory::new(1);
+        memory.protect(0..1, Protection::ReadOnly).unwrap();
+        let ops = [
+            BatchOp {
+                opcode: BatchOpcode::ReadI32 as u32,
+                ..Default::default()
+            },
+            BatchOp {
+                opcode: BatchOpcode::WriteI32 as u32,
+                ..Default::default()
+            },
+        ];
+
+        assert_eq!(
+            memory.execute_batch(&ops, &mut [0; 2]),
+            Err(BatchTrap {
+                index: 1,
+                trap: Trap::Protected
+            })
+        );
+    }
+}
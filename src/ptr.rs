@@ -0,0 +1,401 @@
+use core::fmt;
+use core::marker::PhantomData;
+
+use crate::memory::LinearMemory;
+
+#[cfg(feature = "derive")]
+pub use linmem_derive::GuestLayout;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PtrError {
+    OutOfBounds,
+    Unaligned,
+    /// The values written to a slice don't match its length
+    LengthMismatch,
+}
+
+impl fmt::Display for PtrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PtrError::OutOfBounds => write!(f, "pointer exceeds memory bounds"),
+            PtrError::Unaligned => write!(f, "pointer is not aligned for its type"),
+            PtrError::LengthMismatch => write!(f, "values don't match the slice length"),
+        }
+    }
+}
+
+impl core::error::Error for PtrError {}
+
+/// Plain data with a fixed wasm32 layout, stored little endian whatever the host.
+pub trait GuestLayout: Copy {
+    const SIZE: usize;
+    const ALIGN: usize;
+
+    /// Reads a value from `address`, which the caller has bounds checked.
+    fn load_from(memory: &LinearMemory, address: i32) -> Self;
+
+    /// Writes the value to `address`, which the caller has bounds checked.
+    fn store_to(&self, memory: &mut LinearMemory, address: i32);
+}
+
+/// Guest types whose host layout is identical to their guest layout, so memory can be viewed as
+/// a slice of them without copying.
+///
+/// # Safety
+///
+/// The type must have the size and alignment of its `GuestLayout`, be valid for every bit pattern
+/// and hold no padding.
+pub unsafe trait ZeroCopy: GuestLayout {}
+
+macro_rules! primitive_layouts {
+    ($($type:ty => $read:ident, $write:ident, $via:ty;)*) => {
+        $(
+            impl GuestLayout for $type {
+                const SIZE: usize = size_of::<$type>();
+                const ALIGN: usize = size_of::<$type>();
+
+                fn load_from(memory: &LinearMemory, address: i32) -> Self {
+                    memory.$read(address) as $type
+                }
+
+                fn store_to(&self, memory: &mut LinearMemory, address: i32) {
+                    memory.$write(address, *self as $via)
+                }
+            }
+
+            #[cfg(target_endian = "little")]
+            unsafe impl ZeroCopy for $type {}
+        )*
+    };
+}
+
+primitive_layouts! {
+    i8 => read_i32_from_i8, write_i32_to_i8, i32;
+    u8 => read_i32_from_u8, write_i32_to_u8, i32;
+    i16 => read_i32_from_i16, write_i32_to_i16, i32;
+    u16 => read_i32_from_u16, write_i32_to_u16, i32;
+    i32 => read_i32, write_i32, i32;
+    u32 => read_i64_from_u32, write_i64_to_u32, i64;
+    i64 => read_i64, write_i64, i64;
+    u64 => read_i64, write_i64, i64;
+    f32 => read_f32, write_f32, f32;
+    f64 => read_f64, write_f64, f64;
+}
+
+impl<T: GuestLayout, const N: usize> GuestLayout for [T; N] {
+    const SIZE: usize = T::SIZE * N;
+    const ALIGN: usize = T::ALIGN;
+
+    fn load_from(memory: &LinearMemory, address: i32) -> Self {
+        core::array::from_fn(|index| T::load_from(memory, address + (index * T::SIZE) as i32))
+    }
+
+    fn store_to(&self, memory: &mut LinearMemory, address: i32) {
+        for (index, value) in self.iter().enumerate() {
+            value.store_to(memory, address + (index * T::SIZE) as i32);
+        }
+    }
+}
+
+unsafe impl<T: ZeroCopy, const N: usize> ZeroCopy for [T; N] {}
+
+/// Rounds `offset` up to a multiple of `align`, used by `#[derive(GuestLayout)]` to place fields.
+#[doc(hidden)]
+pub const fn align_up(offset: usize, align: usize) -> usize {
+    offset.next_multiple_of(align)
+}
+
+/// Checks that `count` values of `T` fit at `offset` and that it is aligned for `T`.
+fn checked_address<T: GuestLayout>(
+    memory: &LinearMemory,
+    offset: u32,
+    count: u32,
+) -> Result<i32, PtrError> {
+    let address = i32::try_from(offset).map_err(|_| PtrError::OutOfBounds)?;
+    let byte_count = (count as usize)
+        .checked_mul(T::SIZE)
+        .ok_or(PtrError::OutOfBounds)?;
+    memory
+        .checked_range(address, byte_count)
+        .ok_or(PtrError::OutOfBounds)?;
+    if !(offset as usize).is_multiple_of(T::ALIGN) {
+        return Err(PtrError::Unaligned);
+    }
+    Ok(address)
+}
+
+/// Typed guest pointer, a 32 bit offset into a `LinearMemory`.
+#[repr(transparent)]
+pub struct WasmPtr<T> {
+    offset: u32,
+    marker: PhantomData<fn() -> T>,
+}
+
+// Implemented by hand so they don't require `T` to implement them
+impl<T> Clone for WasmPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for WasmPtr<T> {}
+
+impl<T> PartialEq for WasmPtr<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset
+    }
+}
+
+impl<T> Eq for WasmPtr<T> {}
+
+impl<T> fmt::Debug for WasmPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WasmPtr({:#x})", self.offset)
+    }
+}
+
+impl<T: GuestLayout> WasmPtr<T> {
+    pub const fn new(offset: u32) -> Self {
+        Self {
+            offset,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn offset(self) -> u32 {
+        self.offset
+    }
+
+    pub fn is_null(self) -> bool {
+        self.offset == 0
+    }
+
+    /// Pointer `count` values further on, None if it overflows the 32 bit address space.
+    pub fn checked_add(self, count: u32) -> Option<Self> {
+        let bytes = u32::try_from(T::SIZE).ok()?.checked_mul(count)?;
+        self.offset.checked_add(bytes).map(Self::new)
+    }
+
+    /// Slice of `len` values starting at the pointer.
+    pub fn slice(self, len: u32) -> WasmSlice<T> {
+        WasmSlice::new(self.offset, len)
+    }
+
+    pub fn read(self, memory: &LinearMemory) -> Result<T, PtrError> {
+        let address = checked_address::<T>(memory, self.offset, 1)?;
+        Ok(T::load_from(memory, address))
+    }
+
+    pub fn write(self, memory: &mut LinearMemory, value: T) -> Result<(), PtrError> {
+        let address = checked_address::<T>(memory, self.offset, 1)?;
+        value.store_to(memory, address);
+        Ok(())
+    }
+}
+
+impl<T: GuestLayout> GuestLayout for WasmPtr<T> {
+    const SIZE: usize = size_of::<u32>();
+    const ALIGN: usize = size_of::<u32>();
+
+    fn load_from(memory: &LinearMemory, address: i32) -> Self {
+        Self::new(u32::load_from(memory, address))
+    }
+
+    fn store_to(&self, memory: &mut LinearMemory, address: i32) {
+        self.offset.store_to(memory, address)
+    }
+}
+
+/// Typed guest slice of `len` values starting at `offset`.
+pub struct WasmSlice<T> {
+    offset: u32,
+    len: u32,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for WasmSlice<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for WasmSlice<T> {}
+
+impl<T> fmt::Debug for WasmSlice<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WasmSlice({:#x}, {})", self.offset, self.len)
+    }
+}
+
+impl<T: GuestLayout> WasmSlice<T> {
+    pub const fn new(offset: u32, len: u32) -> Self {
+        Self {
+            offset,
+            len,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn len(self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.len == 0
+    }
+
+    /// Pointer to the value at `index`, None past the end.
+    pub fn index(self, index: u32) -> Option<WasmPtr<T>> {
+        (index < self.len)
+            .then(|| WasmPtr::new(self.offset).checked_add(index))
+            .flatten()
+    }
+
+    /// Checks the whole slice once, then yields its values.
+    pub fn iter(
+        self,
+        memory: &LinearMemory,
+    ) -> Result<impl ExactSizeIterator<Item = T> + '_, PtrError> {
+        let address = checked_address::<T>(memory, self.offset, self.len)?;
+        Ok((0..self.len as usize)
+            .map(move |index| T::load_from(memory, address + (index * T::SIZE) as i32)))
+    }
+
+    /// Writes `values` over the slice, failing without writing unless they match its length.
+    pub fn write(self, memory: &mut LinearMemory, values: &[T]) -> Result<(), PtrError> {
+        if values.len() != self.len as usize {
+            return Err(PtrError::LengthMismatch);
+        }
+        let address = checked_address::<T>(memory, self.offset, self.len)?;
+        for (index, value) in values.iter().enumerate() {
+            value.store_to(memory, address + (index * T::SIZE) as i32);
+        }
+        Ok(())
+    }
+
+    /// Views the slice in place without copying.
+    pub fn as_slice(self, memory: &LinearMemory) -> Result<&[T], PtrError>
+    where
+        T: ZeroCopy,
+    {
+        let address = checked_address::<T>(memory, self.offset, self.len)?;
+        let bytes = memory.read_bytes(address, self.len as usize * T::SIZE);
+        // Safety: the base is 8 byte aligned so the checked guest alignment holds on the host too
+        Ok(unsafe { core::slice::from_raw_parts(bytes.as_ptr().cast(), self.len as usize) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wasm_ptr() {
+        let mut memory = LinearMemory::new(1);
+        let pointer = WasmPtr::<u32>::new(16);
+
+        pointer.write(&mut memory, 0xDEAD_BEEF).unwrap();
+        assert_eq!(memory.read_bytes(16, 4), [0xEF, 0xBE, 0xAD, 0xDE]);
+        assert_eq!(pointer.read(&memory), Ok(0xDEAD_BEEF));
+        assert_eq!(pointer.checked_add(2), Some(WasmPtr::new(24)));
+
+        assert_eq!(
+            WasmPtr::<u32>::new(18).read(&memory),
+            Err(PtrError::Unaligned)
+        );
+        assert_eq!(
+            WasmPtr::<u64>::new(memory.size() as u32 - 4).read(&memory),
+            Err(PtrError::OutOfBounds)
+        );
+        assert_eq!(
+            WasmPtr::<u8>::new(u32::MAX).read(&memory),
+            Err(PtrError::OutOfBounds)
+        );
+
+        let nested = WasmPtr::<WasmPtr<u32>>::new(32);
+        nested.write(&mut memory, pointer).unwrap();
+        assert_eq!(nested.read(&memory).unwrap().read(&memory), Ok(0xDEAD_BEEF));
+    }
+
+    #[test]
+    fn test_wasm_slice() {
+        let mut memory = LinearMemory::new(1);
+        let slice = WasmSlice::<i16>::new(64, 3);
+
+        slice.write(&mut memory, &[-1, 2, 300]).unwrap();
+        assert_eq!(
+            slice.write(&mut memory, &[7]),
+            Err(PtrError::LengthMismatch)
+        );
+        assert_eq!(
+            slice.iter(&memory).unwrap().collect::<Vec<_>>(),
+            [-1, 2, 300]
+        );
+        assert_eq!(slice.as_slice(&memory), Ok(&[-1, 2, 300][..]));
+        assert_eq!(slice.index(2).unwrap().read(&memory), Ok(300));
+        assert!(slice.index(3).is_none());
+
+        let arrays = WasmSlice::<[u8; 3]>::new(64, 2);
+        assert_eq!(
+            arrays.iter(&memory).unwrap().nth(1),
+            Some([0x00, 0x2C, 0x01])
+        );
+        assert!(WasmSlice::<f64>::new(4, 1).iter(&memory).is_err());
+        assert_eq!(
+            WasmSlice::<u8>::new(0, u32::MAX).as_slice(&memory),
+            Err(PtrError::OutOfBounds)
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_guest_layout() {
+        // struct iovec { uint8_t *buf; size_t len; } and a padded neighbour, as clang lays them out
+        #[derive(Clone, Copy, Debug, PartialEq, GuestLayout)]
+        struct Iovec {
+            buffer: WasmPtr<u8>,
+            length: u32,
+        }
+
+        #[derive(Clone, Copy, Debug, PartialEq, GuestLayout)]
+        struct Filestat {
+            kind: u8,
+            size: u64,
+            vectors: [Iovec; 2],
+            flags: u16,
+        }
+
+        #[derive(Clone, Copy, Debug, PartialEq, GuestLayout)]
+        struct Pair(i8, f32);
+
+        assert_eq!((Iovec::SIZE, Iovec::ALIGN), (8, 4));
+        assert_eq!((Filestat::SIZE, Filestat::ALIGN), (40, 8));
+        assert_eq!((Pair::SIZE, Pair::ALIGN), (8, 4));
+
+        let mut memory = LinearMemory::new(1);
+        let stat = Filestat {
+            kind: 3,
+            size: 1 << 40,
+            vectors: [Iovec {
+                buffer: WasmPtr::new(0x100),
+                length: 12,
+            }; 2],
+            flags: 0xBEEF,
+        };
+        let pointer = WasmPtr::new(64);
+        pointer.write(&mut memory, stat).unwrap();
+        assert_eq!(memory.read_i32_from_u8(64), 3);
+        assert_eq!(memory.read_i64(72), 1 << 40);
+        assert_eq!(memory.read_i32(88), 0x100);
+        assert_eq!(memory.read_i32(92), 12);
+        assert_eq!(memory.read_i32_from_u16(96), 0xBEEF);
+        assert_eq!(pointer.read(&memory), Ok(stat));
+
+        WasmPtr::new(4).write(&mut memory, Pair(-2, 0.5)).unwrap();
+        assert_eq!(memory.read_f32(8), 0.5);
+        assert_eq!(
+            WasmPtr::<Pair>::new(6).read(&memory),
+            Err(PtrError::Unaligned)
+        );
+    }
+}
@@ -0,0 +1,359 @@
+use concurrent_queue::ConcurrentQueue;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use crate::memory::{AccessKind, Operation};
+
+static NEXT_THREAD_ID: AtomicU32 = AtomicU32::new(0);
+
+thread_local! {
+    static THREAD_ID: u32 = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Identifier the race detector uses for the calling host thread.
+pub fn current_thread_id() -> u32 {
+    THREAD_ID.with(|id| *id)
+}
+
+/// One side of a data race.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RaceAccess {
+    pub thread: u32,
+    pub address: i32,
+    pub kind: AccessKind,
+    pub operation: Operation,
+}
+
+/// Two conflicting accesses to the same byte which are not ordered by happens-before.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DataRace {
+    pub previous: RaceAccess,
+    pub current: RaceAccess,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum SyncKey {
+    Address(i32),
+    Fence,
+    Host(u64),
+}
+
+#[derive(Clone, Debug, Default)]
+struct VectorClock(Vec<u32>);
+
+impl VectorClock {
+    fn get(&self, thread: u32) -> u32 {
+        self.0.get(thread as usize).copied().unwrap_or(0)
+    }
+
+    fn tick(&mut self, thread: u32) {
+        let index = thread as usize;
+        if self.0.len() <= index {
+            self.0.resize(index + 1, 0);
+        }
+        self.0[index] += 1;
+    }
+
+    fn join(&mut self, other: &VectorClock) {
+        if self.0.len() < other.0.len() {
+            self.0.resize(other.0.len(), 0);
+        }
+        for (mine, theirs) in self.0.iter_mut().zip(&other.0) {
+            *mine = (*mine).max(*theirs);
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Epoch {
+    clock: u32,
+    atomic: bool,
+    access: RaceAccess,
+}
+
+#[derive(Default)]
+struct Location {
+    write: Option<Epoch>,
+    reads: Vec<Epoch>,
+}
+
+#[derive(Default)]
+struct State {
+    clocks: HashMap<u32, VectorClock>,
+    sync: HashMap<SyncKey, VectorClock>,
+    locations: HashMap<usize, Location>,
+}
+
+impl State {
+    fn clock(&mut self, thread: u32) -> &mut VectorClock {
+        self.clocks.entry(thread).or_insert_with(|| {
+            let mut clock = VectorClock::default();
+            clock.tick(thread);
+            clock
+        })
+    }
+
+    fn acquire(&mut self, thread: u32, key: SyncKey) {
+        if let Some(released) = self.sync.get(&key).cloned() {
+            self.clock(thread).join(&released);
+        }
+    }
+
+    fn release(&mut self, thread: u32, key: SyncKey) {
+        let clock = self.clock(thread).clone();
+        self.sync.entry(key).or_default().join(&clock);
+        self.clock(thread).tick(thread);
+    }
+}
+
+/// Most races kept waiting to be taken, later ones are only counted until some are taken.
+pub const MAX_DATA_RACES: usize = 1024;
+
+pub(crate) struct RaceDetector {
+    state: Mutex<State>,
+    races: ConcurrentQueue<DataRace>,
+    dropped: AtomicU64,
+}
+
+impl Default for RaceDetector {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            races: ConcurrentQueue::bounded(MAX_DATA_RACES),
+            dropped: AtomicU64::new(0),
+        }
+    }
+}
+
+impl RaceDetector {
+    /// Records the access and reports the first unordered conflict, atomics synchronise while
+    /// the detector lock is held so the edge they create matches the value they observed.
+    pub(crate) fn observe<R>(
+        &self,
+        address: i32,
+        byte_count: usize,
+        kind: AccessKind,
+        operation: Operation,
+        access: impl FnOnce() -> R,
+    ) -> R {
+        let thread = current_thread_id();
+        let atomic = matches!(
+            operation,
+            Operation::AtomicLoad
+                | Operation::AtomicStore
+                | Operation::AtomicRmw
+                | Operation::AtomicCompareExchange
+        );
+
+        let mut state = self.state.lock();
+        if atomic && kind.overlaps(AccessKind::Read) {
+            state.acquire(thread, SyncKey::Address(address));
+        }
+
+        let result = access();
+
+        let current = RaceAccess {
+            thread,
+            address,
+            kind,
+            operation,
+        };
+        if let Some(previous) = self.record(&mut state, current, byte_count, atomic) {
+            if self.races.push(DataRace { previous, current }).is_err() {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        if atomic && kind.overlaps(AccessKind::Write) {
+            state.release(thread, SyncKey::Address(address));
+        }
+        result
+    }
+
+    fn record(
+        &self,
+        state: &mut State,
+        current: RaceAccess,
+        byte_count: usize,
+        atomic: bool,
+    ) -> Option<RaceAccess> {
+        let clock = state.clock(current.thread).clone();
+        let epoch = Epoch {
+            clock: clock.get(current.thread),
+            atomic,
+            access: current,
+        };
+        let unordered = |previous: &Epoch| {
+            previous.access.thread != current.thread
+                && !(atomic && previous.atomic)
+                && previous.clock > clock.get(previous.access.thread)
+        };
+
+        let mut race = None;
+        let start = current.address as usize;
+        for byte in start..start + byte_count {
+            let location = state.locations.entry(byte).or_default();
+
+            if race.is_none() {
+                race = location
+                    .write
+                    .iter()
+                    .chain(
+                        location
+                            .reads
+                            .iter()
+                            .filter(|_| current.kind.overlaps(AccessKind::Write)),
+                    )
+                    .find(|previous| unordered(previous))
+                    .map(|previous| previous.access);
+            }
+
+            if current.kind.overlaps(AccessKind::Write) {
+                location.write = Some(epoch);
+                location.reads.clear();
+            } else {
+                location
+                    .reads
+                    .retain(|read| read.access.thread != current.thread);
+                location.reads.push(epoch);
+            }
+        }
+        race
+    }
+
+    pub(crate) fn acquire(&self, key: SyncKey) {
+        self.state.lock().acquire(current_thread_id(), key);
+    }
+
+    pub(crate) fn release(&self, key: SyncKey) {
+        self.state.lock().release(current_thread_id(), key);
+    }
+
+    pub(crate) fn fence(&self) {
+        let thread = current_thread_id();
+        let mut state = self.state.lock();
+        state.acquire(thread, SyncKey::Fence);
+        state.release(thread, SyncKey::Fence);
+    }
+
+    pub(crate) fn take_race(&self) -> Option<DataRace> {
+        self.races.pop().ok()
+    }
+
+    pub(crate) fn dropped_races(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::LinearMemory;
+    use std::thread;
+
+    fn on_other_thread(
+        memory: LinearMemory,
+        f: impl FnOnce(&mut LinearMemory) + Send + 'static,
+    ) -> LinearMemory {
+        thread::spawn(move || {
+            let mut memory = memory;
+            f(&mut memory);
+            memory
+        })
+        .join()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_unsynchronised_write_read_is_reported() {
+        let mut memory = LinearMemory::new(1);
+        memory.enable_race_detector();
+        memory.write_i64(16, 1);
+
+        let memory = on_other_thread(memory, |memory| {
+            let _ = memory.read_i32(20);
+        });
+
+        let race = memory.take_data_race().unwrap();
+        assert_eq!(race.previous.address, 16);
+        assert_eq!(race.previous.kind, AccessKind::Write);
+        assert_eq!(race.previous.thread, current_thread_id());
+        assert_eq!(race.current.address, 20);
+        assert_eq!(race.current.kind, AccessKind::Read);
+        assert_ne!(race.current.thread, race.previous.thread);
+        assert_eq!(memory.take_data_race(), None);
+    }
+
+    #[test]
+    fn test_data_races_are_capped() {
+        let mut memory = LinearMemory::new(1);
+        memory.enable_race_detector();
+        let count = MAX_DATA_RACES as i32 + 3;
+        for index in 0..count {
+            memory.write_i32(index * 4, 1);
+        }
+
+        let memory = on_other_thread(memory, move |memory| {
+            for index in 0..count {
+                let _ = memory.read_i32(index * 4);
+            }
+        });
+
+        assert_eq!(memory.dropped_data_races(), 3);
+        let mut taken = 0;
+        while memory.take_data_race().is_some() {
+            taken += 1;
+        }
+        assert_eq!(taken, MAX_DATA_RACES);
+    }
+
+    #[test]
+    fn test_atomics_establish_happens_before() {
+        let mut memory = LinearMemory::new(1);
+        memory.enable_race_detector();
+        memory.write_i32(64, 7);
+        memory.atomic_write_i32(0, 1);
+
+        let memory = on_other_thread(memory, |memory| {
+            assert_eq!(memory.atomic_read_i32(0), 1);
+            assert_eq!(memory.read_i32(64), 7);
+            memory.write_i32(64, 8);
+        });
+
+        assert_eq!(memory.take_data_race(), None);
+    }
+
+    #[test]
+    fn test_host_synchronisation() {
+        let mut memory = LinearMemory::new(1);
+        memory.enable_race_detector();
+        memory.fill(0, 32, 1);
+        memory.race_release(1);
+
+        let memory = on_other_thread(memory, |memory| {
+            memory.race_acquire(1);
+            memory.fill(0, 32, 2);
+        });
+
+        assert_eq!(memory.take_data_race(), None);
+    }
+
+    #[test]
+    fn test_atomic_accesses_do_not_race() {
+        let mut memory = LinearMemory::new(1);
+        memory.enable_race_detector();
+        memory.atomic_rmw_add_i32(0, 1);
+
+        let mut memory = on_other_thread(memory, |memory| {
+            memory.atomic_rmw_add_i32(0, 1);
+        });
+        assert_eq!(memory.take_data_race(), None);
+
+        assert_eq!(memory.atomic_read_i32(0), 2);
+        memory.write_i32(0, 0);
+        assert_eq!(memory.take_data_race(), None);
+    }
+}
@@ -0,0 +1,357 @@
+use parking_lot::{Condvar, Mutex, MutexGuard};
+use std::cell::Cell;
+use std::sync::Arc;
+
+use crate::wait::{WaitEntry, WAIT_TERMINATED, WAIT_TIMED_OUT};
+
+/// Point at which the scheduler made a choice, recorded so replays can detect divergence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecisionKind {
+    /// Which thread runs next, or which timed wait times out and then runs
+    Schedule,
+    /// Which of the waiters on an address a `notify` wakes
+    Wake(i32),
+}
+
+/// One choice of `choice` among `options`. Choices with a single option are not recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Decision {
+    pub kind: DecisionKind,
+    pub options: u32,
+    pub choice: u32,
+}
+
+enum Status {
+    Runnable,
+    Waiting { entry: Arc<WaitEntry>, timed: bool },
+    Finished,
+}
+
+enum Source {
+    Seeded(u64),
+    Replay(Vec<Decision>),
+}
+
+struct State {
+    threads: Vec<Status>,
+    running: Option<u32>,
+    source: Source,
+    log: Vec<Decision>,
+    deadlocked: bool,
+}
+
+impl State {
+    fn decide(&mut self, kind: DecisionKind, options: usize) -> usize {
+        if options == 1 {
+            return 0;
+        }
+        let position = self.log.len();
+        let choice = match &mut self.source {
+            Source::Seeded(state) => (split_mix(state) % options as u64) as u32,
+            Source::Replay(log) => {
+                let decision = log
+                    .get(position)
+                    .unwrap_or_else(|| panic!("Replay log ended at decision {position}"));
+                assert!(
+                    decision.kind == kind && decision.options == options as u32,
+                    "Replay diverged at decision {position}: expected {decision:?}, found {kind:?} \
+                     with {options} options"
+                );
+                decision.choice
+            }
+        };
+        self.log.push(Decision {
+            kind,
+            options: options as u32,
+            choice,
+        });
+        choice as usize
+    }
+}
+
+fn split_mix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+thread_local! {
+    /// Scheduler and thread id of the calling thread while it is inside `enter`.
+    static CURRENT: Cell<Option<(usize, u32)>> = const { Cell::new(None) };
+}
+
+/// Runs registered guest threads one at a time, choosing with a seeded PRNG which thread runs at
+/// every atomic, wait and notify, which waiter a notify wakes and whether a timed wait times out.
+/// Every choice is logged so a failing run can be replayed exactly with `replay`.
+///
+/// Timed waits never time out by the clock, only when the scheduler picks them. Once every
+/// registered thread is waiting the run has deadlocked, all of them wake with `WAIT_TERMINATED`.
+/// Threads which are not registered, such as the host, bypass the scheduler.
+pub struct DeterministicScheduler {
+    state: Mutex<State>,
+    turn: Condvar,
+}
+
+impl DeterministicScheduler {
+    pub fn new(seed: u64) -> Self {
+        Self::with_source(Source::Seeded(seed))
+    }
+
+    /// Repeats the choices of an earlier run's `log`, panicking if the run diverges from it.
+    pub fn replay(log: Vec<Decision>) -> Self {
+        Self::with_source(Source::Replay(log))
+    }
+
+    fn with_source(source: Source) -> Self {
+        Self {
+            state: Mutex::new(State {
+                threads: Vec::new(),
+                running: None,
+                source,
+                log: Vec::new(),
+                deadlocked: false,
+            }),
+            turn: Condvar::new(),
+        }
+    }
+
+    /// Adds a guest thread, returning the id it passes to `enter`. Threads must be registered in
+    /// the same order on every run, before any of them enters or from a scheduled thread.
+    pub fn register(&self) -> u32 {
+        let mut state = self.state.lock();
+        state.threads.push(Status::Runnable);
+        state.threads.len() as u32 - 1
+    }
+
+    /// Makes the calling thread the registered thread `id` until the guard is dropped, blocking
+    /// until the scheduler first picks it.
+    pub fn enter(self: &Arc<Self>, id: u32) -> ScheduledThread {
+        CURRENT.set(Some((self.key(), id)));
+        let mut state = self.state.lock();
+        if state.running.is_none() {
+            self.schedule(&mut state);
+        }
+        self.wait_turn(state, id);
+        ScheduledThread {
+            scheduler: self.clone(),
+            id,
+        }
+    }
+
+    /// Choices made so far.
+    pub fn log(&self) -> Vec<Decision> {
+        self.state.lock().log.clone()
+    }
+
+    /// True once every registered thread ended up waiting and the waits were terminated.
+    pub fn deadlocked(&self) -> bool {
+        self.state.lock().deadlocked
+    }
+
+    fn key(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    /// The calling thread's id if it is scheduled by this scheduler.
+    fn current(&self) -> Option<u32> {
+        CURRENT
+            .get()
+            .filter(|&(key, _)| key == self.key())
+            .map(|(_, id)| id)
+    }
+
+    /// Lets the scheduler switch to another thread before the calling thread continues.
+    pub(crate) fn yield_now(&self) {
+        let Some(id) = self.current() else {
+            return;
+        };
+        let mut state = self.state.lock();
+        self.schedule(&mut state);
+        self.wait_turn(state, id);
+    }
+
+    /// Blocks the calling thread until `entry`, already queued, completes. Returns None if the
+    /// thread is not scheduled, the caller then waits normally.
+    pub(crate) fn block(&self, entry: Arc<WaitEntry>, timed: bool) -> Option<i32> {
+        let id = self.current()?;
+        let mut state = self.state.lock();
+        state.threads[id as usize] = Status::Waiting {
+            entry: entry.clone(),
+            timed,
+        };
+        self.schedule(&mut state);
+        self.wait_turn(state, id);
+        entry.result()
+    }
+
+    pub(crate) fn is_scheduled(&self) -> bool {
+        self.current().is_some()
+    }
+
+    /// Picks which of `options` waiters a notify wakes.
+    pub(crate) fn choose_waiter(&self, address: i32, options: usize) -> usize {
+        self.state
+            .lock()
+            .decide(DecisionKind::Wake(address), options)
+    }
+
+    fn wait_turn(&self, mut state: MutexGuard<'_, State>, id: u32) {
+        while state.running != Some(id) {
+            self.turn.wait(&mut state);
+        }
+    }
+
+    fn schedule(&self, state: &mut State) {
+        for status in &mut state.threads {
+            if let Status::Waiting { entry, .. } = status {
+                if entry.result().is_some() {
+                    *status = Status::Runnable;
+                }
+            }
+        }
+
+        let mut options = Vec::new();
+        for (id, status) in state.threads.iter().enumerate() {
+            match status {
+                Status::Runnable => options.push((id, false)),
+                Status::Waiting { timed: true, .. } => options.push((id, true)),
+                _ => {}
+            }
+        }
+        if options.is_empty() {
+            let mut waiting = false;
+            for (id, status) in state.threads.iter_mut().enumerate() {
+                if let Status::Waiting { entry, .. } = status {
+                    entry.resolve(WAIT_TERMINATED);
+                    *status = Status::Runnable;
+                    options.push((id, false));
+                    waiting = true;
+                }
+            }
+            state.deadlocked |= waiting;
+        }
+
+        state.running = match options.len() {
+            0 => None,
+            count => {
+                let (id, time_out) = options[state.decide(DecisionKind::Schedule, count)];
+                if time_out {
+                    if let Status::Waiting { entry, .. } = &state.threads[id] {
+                        entry.resolve(WAIT_TIMED_OUT);
+                    }
+                    state.threads[id] = Status::Runnable;
+                }
+                Some(id as u32)
+            }
+        };
+        self.turn.notify_all();
+    }
+}
+
+/// Guard returned by `DeterministicScheduler::enter`, the thread leaves the schedule on drop.
+pub struct ScheduledThread {
+    scheduler: Arc<DeterministicScheduler>,
+    id: u32,
+}
+
+impl Drop for ScheduledThread {
+    fn drop(&mut self) {
+        CURRENT.set(None);
+        let mut state = self.scheduler.state.lock();
+        state.threads[self.id as usize] = Status::Finished;
+        if state.running == Some(self.id) {
+            self.scheduler.schedule(&mut state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::LinearMemory;
+    use crate::wait::WAIT_OK;
+    use std::collections::HashSet;
+    use std::thread;
+
+    /// Two threads racing to append their id, returning the final order and the log.
+    fn run(scheduler: DeterministicScheduler) -> (Vec<i32>, Vec<Decision>) {
+        let scheduler = Arc::new(scheduler);
+        let mut memory = LinearMemory::new(1);
+        memory.set_scheduler(scheduler.clone());
+        let memory = Arc::new(memory);
+
+        let threads: Vec<_> = (0..2)
+            .map(|_| scheduler.register())
+            .map(|id| {
+                let scheduler = scheduler.clone();
+                let memory = memory.clone();
+                thread::spawn(move || {
+                    let _scheduled = scheduler.enter(id);
+                    for _ in 0..4 {
+                        let slot = memory.atomic_rmw_add_i32(0, 1);
+                        memory.atomic_write_i32(4 + slot * 4, id as i32);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let order = (0..8).map(|slot| memory.read_i32(4 + slot * 4)).collect();
+        (order, scheduler.log())
+    }
+
+    #[test]
+    fn test_same_seed_same_interleaving() {
+        let (order, log) = run(DeterministicScheduler::new(7));
+
+        assert_eq!(run(DeterministicScheduler::new(7)).0, order);
+        assert_eq!(
+            run(DeterministicScheduler::replay(log.clone())),
+            (order, log)
+        );
+        let orders: HashSet<_> = (0..16)
+            .map(|seed| run(DeterministicScheduler::new(seed)).0)
+            .collect();
+        assert!(orders.len() > 1);
+    }
+
+    #[test]
+    fn test_wait_notify_and_deadlock() {
+        let scheduler = Arc::new(DeterministicScheduler::new(3));
+        let mut memory = LinearMemory::new(1);
+        memory.set_scheduler(scheduler.clone());
+        let memory = Arc::new(memory);
+        let waiter = scheduler.register();
+        let notifier = scheduler.register();
+
+        let waiting = thread::spawn({
+            let scheduler = scheduler.clone();
+            let memory = memory.clone();
+            move || {
+                let _scheduled = scheduler.enter(waiter);
+                let first = memory.wait_i32(0, 0, -1);
+                // Nobody is left to notify, so this wait deadlocks
+                (first, memory.wait_i32(0, 0, -1))
+            }
+        });
+        thread::spawn({
+            let scheduler = scheduler.clone();
+            let memory = memory.clone();
+            move || {
+                let _scheduled = scheduler.enter(notifier);
+                while memory.notify(0, 1) == 0 {
+                    scheduler.yield_now();
+                }
+            }
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(waiting.join().unwrap(), (WAIT_OK, WAIT_TERMINATED));
+        assert!(scheduler.deadlocked());
+    }
+}
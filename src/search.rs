@@ -0,0 +1,186 @@
+use core::cmp::Ordering;
+
+// The portable backend replaces runtime dispatch, the others remain for its tests
+#[cfg(target_arch = "aarch64")]
+#[cfg_attr(feature = "portable-simd", allow(dead_code))]
+mod neon;
+#[cfg(feature = "portable-simd")]
+mod portable;
+#[cfg_attr(feature = "portable-simd", allow(dead_code))]
+mod scalar;
+mod vector;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[cfg_attr(feature = "portable-simd", allow(dead_code))]
+mod x86;
+
+/// Search kernels for one instruction set. Vector backends are tokens which can only be built once
+/// the CPU is known to support them.
+pub(crate) trait Backend: Copy {
+    /// Returns the offset of the first occurrence of `byte` in `haystack`.
+    fn find_byte(self, haystack: &[u8], byte: u8) -> Option<usize>;
+
+    /// Returns the offset of the first occurrence of `needle` in `haystack`.
+    fn find_pattern(self, haystack: &[u8], needle: &[u8]) -> Option<usize>;
+
+    /// Lexicographically compares `a` and `b` the way `memcmp` does, bytes are unsigned.
+    fn compare(self, a: &[u8], b: &[u8]) -> Ordering;
+}
+
+/// Runs `$call` with the best backend for the running CPU. Detection is cached by `std`, without
+/// it the backend is fixed by the target features the crate is compiled with.
+macro_rules! dispatch {
+    (|$backend:ident| $call:expr) => {{
+        #[cfg(feature = "portable-simd")]
+        {
+            let $backend = portable::Portable;
+            $call
+        }
+        #[cfg(not(feature = "portable-simd"))]
+        {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            {
+                if let Some($backend) = x86::Avx2::detect() {
+                    return $call;
+                }
+                if let Some($backend) = x86::Sse2::detect() {
+                    return $call;
+                }
+            }
+            #[cfg(target_arch = "aarch64")]
+            if let Some($backend) = neon::Neon::detect() {
+                return $call;
+            }
+            let $backend = scalar::Scalar;
+            $call
+        }
+    }};
+}
+
+pub(crate) fn find_byte(haystack: &[u8], byte: u8) -> Option<usize> {
+    dispatch!(|backend| backend.find_byte(haystack, byte))
+}
+
+pub(crate) fn find_pattern(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    dispatch!(|backend| backend.find_pattern(haystack, needle))
+}
+
+pub(crate) fn compare(a: &[u8], b: &[u8]) -> Ordering {
+    dispatch!(|backend| backend.compare(a, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn haystack() -> Vec<u8> {
+        (0..100u8).map(|byte| byte % 50 + 1).collect()
+    }
+
+    fn check_find_byte(backend: impl Backend) {
+        let haystack = haystack();
+
+        assert_eq!(backend.find_byte(&haystack, 1), Some(0));
+        assert_eq!(backend.find_byte(&haystack, 20), Some(19));
+        assert_eq!(backend.find_byte(&haystack[51..], 50), Some(48));
+        assert_eq!(backend.find_byte(&haystack, 0), None);
+        assert_eq!(backend.find_byte(&[], 0), None);
+    }
+
+    fn check_find_pattern(backend: impl Backend) {
+        let haystack = haystack();
+
+        assert_eq!(backend.find_pattern(&haystack, &[]), Some(0));
+        assert_eq!(backend.find_pattern(&haystack, &[5]), Some(4));
+        assert_eq!(backend.find_pattern(&haystack, &[17, 18, 19]), Some(16));
+        assert_eq!(
+            backend.find_pattern(&haystack[20..], &[17, 18, 19]),
+            Some(46)
+        );
+        assert_eq!(backend.find_pattern(&haystack, &[49, 50, 1, 2]), Some(48));
+        assert_eq!(backend.find_pattern(&haystack, &[48, 49, 50]), Some(47));
+        assert_eq!(
+            backend.find_pattern(&haystack[60..], &[48, 49, 50]),
+            Some(37)
+        );
+        assert_eq!(backend.find_pattern(&haystack, &[17, 19]), None);
+        assert_eq!(backend.find_pattern(&[1, 2], &[1, 2, 3]), None);
+    }
+
+    fn check_compare(backend: impl Backend) {
+        let a = haystack();
+        let mut b = a.clone();
+
+        assert_eq!(backend.compare(&a, &b), Ordering::Equal);
+
+        b[40] = 0;
+        assert_eq!(backend.compare(&a, &b), Ordering::Greater);
+        b[40] = 0xFF;
+        assert_eq!(backend.compare(&a, &b), Ordering::Less);
+
+        assert_eq!(backend.compare(&a[..99], &a), Ordering::Less);
+        assert_eq!(backend.compare(&a[..3], &b[..3]), Ordering::Equal);
+    }
+
+    /// Runs `$check` against every backend the host can run.
+    macro_rules! for_each_backend {
+        ($check:ident) => {
+            $check(scalar::Scalar);
+            #[cfg(feature = "portable-simd")]
+            $check(portable::Portable);
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            {
+                if let Some(backend) = x86::Sse2::detect() {
+                    $check(backend);
+                }
+                if let Some(backend) = x86::Avx2::detect() {
+                    $check(backend);
+                }
+            }
+            #[cfg(target_arch = "aarch64")]
+            if let Some(backend) = neon::Neon::detect() {
+                $check(backend);
+            }
+        };
+    }
+
+    #[test]
+    fn test_find_byte() {
+        for_each_backend!(check_find_byte);
+    }
+
+    #[test]
+    fn test_find_pattern() {
+        for_each_backend!(check_find_pattern);
+    }
+
+    #[test]
+    fn test_compare() {
+        for_each_backend!(check_compare);
+    }
+
+    #[test]
+    fn test_backends_agree_with_scalar() {
+        fn check(backend: impl Backend) {
+            let bytes: Vec<u8> = (0..300u32).map(|index| (index * 7 % 11) as u8).collect();
+            for start in 0..40 {
+                let haystack = &bytes[start..];
+                for byte in 0..12 {
+                    assert_eq!(
+                        backend.find_byte(haystack, byte),
+                        scalar::find_byte(haystack, byte)
+                    );
+                }
+                let needle = &bytes[start + 100..start + 103];
+                assert_eq!(
+                    backend.find_pattern(haystack, needle),
+                    scalar::find_pattern(haystack, needle)
+                );
+                assert_eq!(
+                    backend.compare(haystack, &bytes[..bytes.len() - start]),
+                    scalar::compare(haystack, &bytes[..bytes.len() - start])
+                );
+            }
+        }
+        for_each_backend!(check);
+    }
+}
@@ -0,0 +1,69 @@
+use core::arch::aarch64::*;
+use core::cmp::Ordering;
+
+use super::vector::{self, Vector};
+use super::Backend;
+
+impl Vector for uint8x16_t {
+    const LANES: usize = 16;
+    const LANE_BITS: u32 = 4;
+    const LANE_MASK: u64 = 0x8888_8888_8888_8888;
+
+    #[inline(always)]
+    unsafe fn splat(byte: u8) -> Self {
+        vdupq_n_u8(byte)
+    }
+
+    #[inline(always)]
+    unsafe fn load(pointer: *const u8) -> Self {
+        vld1q_u8(pointer)
+    }
+
+    /// NEON has no movemask, shifting each 16 bit pair right by 4 and narrowing leaves a nibble
+    /// per lane of which the top bit is kept.
+    #[inline(always)]
+    unsafe fn eq_mask(self, other: Self) -> u64 {
+        let equal = vreinterpretq_u16_u8(vceqq_u8(self, other));
+        let nibbles = vreinterpret_u64_u8(vshrn_n_u16::<4>(equal));
+        vget_lane_u64::<0>(nibbles) & Self::LANE_MASK
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Neon(());
+
+impl Neon {
+    pub(crate) fn detect() -> Option<Self> {
+        #[cfg(feature = "std")]
+        let detected = std::arch::is_aarch64_feature_detected!("neon");
+        #[cfg(not(feature = "std"))]
+        let detected = cfg!(target_feature = "neon");
+        detected.then_some(Neon(()))
+    }
+}
+
+impl Backend for Neon {
+    fn find_byte(self, haystack: &[u8], byte: u8) -> Option<usize> {
+        #[target_feature(enable = "neon")]
+        unsafe fn kernel(haystack: &[u8], byte: u8) -> Option<usize> {
+            vector::find_byte::<uint8x16_t>(haystack, byte)
+        }
+        unsafe { kernel(haystack, byte) }
+    }
+
+    fn find_pattern(self, haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        #[target_feature(enable = "neon")]
+        unsafe fn kernel(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+            vector::find_pattern::<uint8x16_t>(haystack, needle)
+        }
+        unsafe { kernel(haystack, needle) }
+    }
+
+    fn compare(self, a: &[u8], b: &[u8]) -> Ordering {
+        #[target_feature(enable = "neon")]
+        unsafe fn kernel(a: &[u8], b: &[u8]) -> Ordering {
+            vector::compare::<uint8x16_t>(a, b)
+        }
+        unsafe { kernel(a, b) }
+    }
+}
@@ -0,0 +1,46 @@
+use core::cmp::Ordering;
+use core::simd::{cmp::SimdPartialEq, Simd};
+
+use super::vector::{self, Vector};
+use super::Backend;
+
+type Bytes = Simd<u8, 16>;
+
+impl Vector for Bytes {
+    const LANES: usize = 16;
+    const LANE_BITS: u32 = 1;
+    const LANE_MASK: u64 = 0xFFFF;
+
+    #[inline(always)]
+    unsafe fn splat(byte: u8) -> Self {
+        Bytes::splat(byte)
+    }
+
+    #[inline(always)]
+    unsafe fn load(pointer: *const u8) -> Self {
+        Bytes::from_array(pointer.cast::<[u8; 16]>().read_unaligned())
+    }
+
+    #[inline(always)]
+    unsafe fn eq_mask(self, other: Self) -> u64 {
+        self.simd_eq(other).to_bitmask()
+    }
+}
+
+/// `core::simd` kernels, left to the compiler to lower for the target.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Portable;
+
+impl Backend for Portable {
+    fn find_byte(self, haystack: &[u8], byte: u8) -> Option<usize> {
+        unsafe { vector::find_byte::<Bytes>(haystack, byte) }
+    }
+
+    fn find_pattern(self, haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        unsafe { vector::find_pattern::<Bytes>(haystack, needle) }
+    }
+
+    fn compare(self, a: &[u8], b: &[u8]) -> Ordering {
+        unsafe { vector::compare::<Bytes>(a, b) }
+    }
+}
@@ -0,0 +1,39 @@
+use core::cmp::Ordering;
+
+use super::Backend;
+
+/// Byte at a time fallback for targets without a vector backend, and the tail of every other.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Scalar;
+
+impl Backend for Scalar {
+    fn find_byte(self, haystack: &[u8], byte: u8) -> Option<usize> {
+        find_byte(haystack, byte)
+    }
+
+    fn find_pattern(self, haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        find_pattern(haystack, needle)
+    }
+
+    fn compare(self, a: &[u8], b: &[u8]) -> Ordering {
+        compare(a, b)
+    }
+}
+
+pub(super) fn find_byte(haystack: &[u8], byte: u8) -> Option<usize> {
+    haystack.iter().position(|&candidate| candidate == byte)
+}
+
+pub(super) fn find_pattern(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Slices of bytes order like `memcmp`, a shorter prefix sorts first.
+pub(super) fn compare(a: &[u8], b: &[u8]) -> Ordering {
+    a.cmp(b)
+}
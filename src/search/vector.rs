@@ -0,0 +1,100 @@
+use core::cmp::Ordering;
+
+use super::scalar;
+
+/// One SIMD register of bytes, the search kernels are written once on top of it.
+///
+/// Comparisons produce a bitmask holding a single set bit per matching lane, at
+/// `lane * LANE_BITS`. x86 masks use one bit per lane, NEON narrows to a nibble per lane.
+pub(super) trait Vector: Copy {
+    const LANES: usize;
+    const LANE_BITS: u32;
+    /// Every bit a comparison may set
+    const LANE_MASK: u64;
+
+    unsafe fn splat(byte: u8) -> Self;
+
+    /// Loads `LANES` bytes from `pointer`, which needn't be aligned.
+    unsafe fn load(pointer: *const u8) -> Self;
+
+    unsafe fn eq_mask(self, other: Self) -> u64;
+}
+
+#[inline(always)]
+fn lane<V: Vector>(mask: u64) -> usize {
+    (mask.trailing_zeros() / V::LANE_BITS) as usize
+}
+
+#[inline(always)]
+pub(super) unsafe fn find_byte<V: Vector>(haystack: &[u8], byte: u8) -> Option<usize> {
+    let splat = V::splat(byte);
+    let mut offset = 0;
+
+    while offset + V::LANES <= haystack.len() {
+        let mask = V::load(haystack.as_ptr().add(offset)).eq_mask(splat);
+
+        if mask != 0 {
+            return Some(offset + lane::<V>(mask));
+        }
+
+        offset += V::LANES;
+    }
+
+    scalar::find_byte(&haystack[offset..], byte).map(|position| offset + position)
+}
+
+/// Each vector compares the first and last byte of the needle against `LANES` candidate positions
+/// at once, only the candidates matching both are verified in full.
+#[inline(always)]
+pub(super) unsafe fn find_pattern<V: Vector>(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    match needle.len() {
+        0 => return Some(0),
+        1 => return find_byte::<V>(haystack, needle[0]),
+        len if len > haystack.len() => return None,
+        _ => {}
+    }
+
+    let last = needle.len() - 1;
+    let first_splat = V::splat(needle[0]);
+    let last_splat = V::splat(needle[last]);
+    let candidates = haystack.len() - last;
+    let mut offset = 0;
+
+    while offset + V::LANES <= candidates {
+        let first = V::load(haystack.as_ptr().add(offset)).eq_mask(first_splat);
+        let end = V::load(haystack.as_ptr().add(offset + last)).eq_mask(last_splat);
+        let mut mask = first & end;
+
+        while mask != 0 {
+            let candidate = offset + lane::<V>(mask);
+            if haystack[candidate + 1..candidate + last] == needle[1..last] {
+                return Some(candidate);
+            }
+            mask &= mask - 1;
+        }
+
+        offset += V::LANES;
+    }
+
+    scalar::find_pattern(&haystack[offset..], needle).map(|position| offset + position)
+}
+
+#[inline(always)]
+pub(super) unsafe fn compare<V: Vector>(a: &[u8], b: &[u8]) -> Ordering {
+    let len = a.len().min(b.len());
+    let mut offset = 0;
+
+    while offset + V::LANES <= len {
+        let equal = V::load(a.as_ptr().add(offset)).eq_mask(V::load(b.as_ptr().add(offset)));
+        let mask = !equal & V::LANE_MASK;
+
+        if mask != 0 {
+            let index = offset + lane::<V>(mask);
+            return a[index].cmp(&b[index]);
+        }
+
+        offset += V::LANES;
+    }
+
+    scalar::compare(&a[offset..], &b[offset..])
+}
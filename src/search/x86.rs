@@ -0,0 +1,98 @@
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+use core::cmp::Ordering;
+
+use super::vector::{self, Vector};
+use super::Backend;
+
+impl Vector for __m128i {
+    const LANES: usize = 16;
+    const LANE_BITS: u32 = 1;
+    const LANE_MASK: u64 = 0xFFFF;
+
+    #[inline(always)]
+    unsafe fn splat(byte: u8) -> Self {
+        _mm_set1_epi8(byte as i8)
+    }
+
+    #[inline(always)]
+    unsafe fn load(pointer: *const u8) -> Self {
+        _mm_loadu_si128(pointer.cast())
+    }
+
+    #[inline(always)]
+    unsafe fn eq_mask(self, other: Self) -> u64 {
+        _mm_movemask_epi8(_mm_cmpeq_epi8(self, other)) as u32 as u64
+    }
+}
+
+impl Vector for __m256i {
+    const LANES: usize = 32;
+    const LANE_BITS: u32 = 1;
+    const LANE_MASK: u64 = 0xFFFF_FFFF;
+
+    #[inline(always)]
+    unsafe fn splat(byte: u8) -> Self {
+        _mm256_set1_epi8(byte as i8)
+    }
+
+    #[inline(always)]
+    unsafe fn load(pointer: *const u8) -> Self {
+        _mm256_loadu_si256(pointer.cast())
+    }
+
+    #[inline(always)]
+    unsafe fn eq_mask(self, other: Self) -> u64 {
+        _mm256_movemask_epi8(_mm256_cmpeq_epi8(self, other)) as u32 as u64
+    }
+}
+
+/// Implements `Backend` for a token which can only be built once `$feature` is known to be
+/// available, each kernel is compiled with the feature enabled.
+macro_rules! x86_backend {
+    ($backend:ident, $feature:tt, $vector:ty) => {
+        #[derive(Clone, Copy, Debug)]
+        pub(crate) struct $backend(());
+
+        impl $backend {
+            pub(crate) fn detect() -> Option<Self> {
+                #[cfg(feature = "std")]
+                let detected = std::is_x86_feature_detected!($feature);
+                #[cfg(not(feature = "std"))]
+                let detected = cfg!(target_feature = $feature);
+                detected.then_some($backend(()))
+            }
+        }
+
+        impl Backend for $backend {
+            fn find_byte(self, haystack: &[u8], byte: u8) -> Option<usize> {
+                #[target_feature(enable = $feature)]
+                unsafe fn kernel(haystack: &[u8], byte: u8) -> Option<usize> {
+                    vector::find_byte::<$vector>(haystack, byte)
+                }
+                unsafe { kernel(haystack, byte) }
+            }
+
+            fn find_pattern(self, haystack: &[u8], needle: &[u8]) -> Option<usize> {
+                #[target_feature(enable = $feature)]
+                unsafe fn kernel(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+                    vector::find_pattern::<$vector>(haystack, needle)
+                }
+                unsafe { kernel(haystack, needle) }
+            }
+
+            fn compare(self, a: &[u8], b: &[u8]) -> Ordering {
+                #[target_feature(enable = $feature)]
+                unsafe fn kernel(a: &[u8], b: &[u8]) -> Ordering {
+                    vector::compare::<$vector>(a, b)
+                }
+                unsafe { kernel(a, b) }
+            }
+        }
+    };
+}
+
+x86_backend!(Sse2, "sse2", __m128i);
+x86_backend!(Avx2, "avx2", __m256i);
@@ -0,0 +1,279 @@
+use concurrent_queue::ConcurrentQueue;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::error::MemoryError;
+use crate::memory::{AccessKind, Operation};
+
+/// Per byte state tracked by the shadow map.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShadowState {
+    Addressable = 0,
+    Uninitialised = 1,
+    Freed = 2,
+    Redzone = 3,
+}
+
+impl TryFrom<u8> for ShadowState {
+    type Error = MemoryError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ShadowState::Addressable),
+            1 => Ok(ShadowState::Uninitialised),
+            2 => Ok(ShadowState::Freed),
+            3 => Ok(ShadowState::Redzone),
+            _ => Err(MemoryError::InvalidArgument),
+        }
+    }
+}
+
+impl ShadowState {
+    fn from_u8(value: u8) -> Self {
+        Self::try_from(value).unwrap_or(ShadowState::Addressable)
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViolationKind {
+    UseAfterFree,
+    BufferOverflow,
+    UninitialisedRead,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShadowViolation {
+    pub kind: ViolationKind,
+    /// Address of the first offending byte
+    pub address: i32,
+    pub operation: Operation,
+}
+
+/// Most violations kept waiting to be taken, later ones are only counted until some are taken.
+pub const MAX_SHADOW_VIOLATIONS: usize = 1024;
+
+pub(crate) struct ShadowMemory {
+    states: Vec<AtomicU8>,
+    violations: ConcurrentQueue<ShadowViolation>,
+    dropped: AtomicU64,
+}
+
+impl ShadowMemory {
+    pub(crate) fn new(len: usize) -> Self {
+        let mut shadow = Self {
+            states: Vec::new(),
+            violations: ConcurrentQueue::bounded(MAX_SHADOW_VIOLATIONS),
+            dropped: AtomicU64::new(0),
+        };
+        shadow.resize(len);
+        shadow
+    }
+
+    /// Bytes added by a grow are zeroed by the mapping, so they start addressable.
+    pub(crate) fn resize(&mut self, len: usize) {
+        self.states
+            .resize_with(len, || AtomicU8::new(ShadowState::Addressable as u8));
+    }
+
+    pub(crate) fn set(&self, range: Range<usize>, state: ShadowState) {
+        let end = range.end.min(self.states.len());
+        let start = range.start.min(end);
+        for byte in &self.states[start..end] {
+            byte.store(state as u8, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn check(
+        &self,
+        address: i32,
+        byte_count: usize,
+        kind: AccessKind,
+        operation: Operation,
+    ) {
+        let start = (address as usize).min(self.states.len());
+        let end = (start + byte_count).min(self.states.len());
+        let bytes = &self.states[start..end];
+
+        // Copies move uninitialised padding around legitimately, their destination takes on the
+        // state of the source instead so the consumers of the copy are reported
+        let reports_uninitialised = kind.overlaps(AccessKind::Read) && operation != Operation::Copy;
+
+        for (offset, byte) in bytes.iter().enumerate() {
+            let violation = match ShadowState::from_u8(byte.load(Ordering::Relaxed)) {
+                ShadowState::Freed => ViolationKind::UseAfterFree,
+                ShadowState::Redzone => ViolationKind::BufferOverflow,
+                ShadowState::Uninitialised if reports_uninitialised => {
+                    ViolationKind::UninitialisedRead
+                }
+                _ => continue,
+            };
+            let pushed = self.violations.push(ShadowViolation {
+                kind: violation,
+                address: (start + offset) as i32,
+                operation,
+            });
+            if pushed.is_err() {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            return;
+        }
+
+        if kind.overlaps(AccessKind::Write) && operation != Operation::Copy {
+            for byte in bytes {
+                let _ = byte.compare_exchange(
+                    ShadowState::Uninitialised as u8,
+                    ShadowState::Addressable as u8,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                );
+            }
+        }
+    }
+
+    /// Whether each byte of `range` is initialised, for a copy to carry over to its destination.
+    pub(crate) fn initialised(&self, range: Range<usize>) -> Vec<bool> {
+        self.states[range]
+            .iter()
+            .map(|byte| byte.load(Ordering::Relaxed) != ShadowState::Uninitialised as u8)
+            .collect()
+    }
+
+    /// Marks the bytes from `start` initialised or not as `initialised` says, freed and redzone
+    /// bytes keep their state.
+    pub(crate) fn set_initialised(&self, start: usize, initialised: &[bool]) {
+        for (byte, &initialised) in self.states[start..].iter().zip(initialised) {
+            let (from, to) = if initialised {
+                (ShadowState::Uninitialised, ShadowState::Addressable)
+            } else {
+                (ShadowState::Addressable, ShadowState::Uninitialised)
+            };
+            let _ =
+                byte.compare_exchange(from as u8, to as u8, Ordering::Relaxed, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn take_violation(&self) -> Option<ShadowViolation> {
+        self.violations.pop().ok()
+    }
+
+    pub(crate) fn dropped_violations(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::LinearMemory;
+
+    #[test]
+    fn test_shadow_use_after_free() {
+        let mut memory = LinearMemory::new(1);
+        memory.enable_shadow();
+        memory.poison(64..80, ShadowState::Freed);
+
+        let _ = memory.read_i32(72);
+
+        assert_eq!(
+            memory.take_shadow_violation(),
+            Some(ShadowViolation {
+                kind: ViolationKind::UseAfterFree,
+                address: 72,
+                operation: Operation::Load,
+            })
+        );
+        assert_eq!(memory.take_shadow_violation(), None);
+    }
+
+    #[test]
+    fn test_shadow_buffer_overflow() {
+        let mut memory = LinearMemory::new(1);
+        memory.enable_shadow();
+        memory.poison(16..24, ShadowState::Redzone);
+
+        memory.write_bytes(8, &[0xFF; 12]);
+
+        let violation = memory.take_shadow_violation().unwrap();
+        assert_eq!(violation.kind, ViolationKind::BufferOverflow);
+        assert_eq!(violation.address, 16);
+        assert_eq!(violation.operation, Operation::WriteBytes);
+    }
+
+    #[test]
+    fn test_shadow_uninitialised_read() {
+        let mut memory = LinearMemory::new(1);
+        memory.enable_shadow();
+        memory.poison(0..8, ShadowState::Uninitialised);
+
+        memory.write_i32(0, 1);
+        let _ = memory.read_i32(0);
+        assert_eq!(memory.take_shadow_violation(), None);
+
+        let _ = memory.read_i64(0);
+        let violation = memory.take_shadow_violation().unwrap();
+        assert_eq!(violation.kind, ViolationKind::UninitialisedRead);
+        assert_eq!(violation.address, 4);
+    }
+
+    #[test]
+    fn test_shadow_copy_carries_uninitialised_bytes() {
+        let mut memory = LinearMemory::new(1);
+        let mut other = LinearMemory::new(1);
+        memory.enable_shadow();
+        other.enable_shadow();
+        memory.poison(0..8, ShadowState::Uninitialised);
+        memory.write_i32(0, 1);
+
+        memory.copy_within(0, 64, 8);
+        memory.copy(0, &mut other, 32, 8);
+        assert_eq!(memory.take_shadow_violation(), None);
+
+        let _ = memory.read_i32(64);
+        assert_eq!(memory.take_shadow_violation(), None);
+        let _ = memory.read_i32(68);
+        let violation = memory.take_shadow_violation().unwrap();
+        assert_eq!(violation.kind, ViolationKind::UninitialisedRead);
+        assert_eq!(violation.address, 68);
+
+        let _ = other.read_i64(32);
+        let violation = other.take_shadow_violation().unwrap();
+        assert_eq!(violation.kind, ViolationKind::UninitialisedRead);
+        assert_eq!(violation.address, 36);
+    }
+
+    #[test]
+    fn test_shadow_violations_are_capped() {
+        let mut memory = LinearMemory::new(1);
+        memory.enable_shadow();
+        memory.poison(0..8, ShadowState::Freed);
+
+        for _ in 0..MAX_SHADOW_VIOLATIONS + 3 {
+            let _ = memory.read_i32(4);
+        }
+
+        assert_eq!(memory.dropped_shadow_violations(), 3);
+        let mut taken = 0;
+        while let Some(violation) = memory.take_shadow_violation() {
+            assert_eq!(violation.address, 4);
+            taken += 1;
+        }
+        assert_eq!(taken, MAX_SHADOW_VIOLATIONS);
+    }
+
+    #[test]
+    fn test_shadow_unpoison_and_grow() {
+        let mut memory = LinearMemory::new(1);
+        memory.enable_shadow();
+        memory.poison(0..4, ShadowState::Freed);
+        memory.unpoison(0..4);
+        assert!(memory.grow(1));
+
+        memory.atomic_rmw_add_i32(0, 1);
+        memory.fill(70_000, 16, 1);
+
+        assert_eq!(memory.take_shadow_violation(), None);
+    }
+}
@@ -0,0 +1,398 @@
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU16, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use paste::paste;
+
+use crate::error::MemoryError;
+use crate::memory::{LinearMemory, PAGE_SIZE};
+use crate::trap::{self, Trap};
+
+struct Inner {
+    // Reserved at the maximum size and never grown, so the base never moves
+    memory: LinearMemory,
+    base: *mut u8,
+    length: AtomicUsize,
+    maximum: usize,
+}
+
+// Safety: every access through `base` is atomic
+unsafe impl Send for Inner {}
+unsafe impl Sync for Inner {}
+
+/// Handle to a memory shared by several threads, as wasm shared memories are. Every access goes
+/// through `&self`: plain loads and stores use relaxed atomics so racing threads see torn values
+/// at worst rather than undefined behaviour, atomics keep their sequentially consistent ordering.
+///
+/// Out of bounds accesses trap with `Trap::OutOfBounds`, atomics at an address which is not a
+/// multiple of their width with `Trap::UnalignedAtomic`. Watchpoints, the shadow map and the race
+/// detector don't observe plain accesses made through the handle.
+#[derive(Clone)]
+pub struct SharedLinearMemory {
+    inner: Arc<Inner>,
+}
+
+macro_rules! shared_read_writers {
+    ($(($($item:ty),+)),* $(,)?) => {
+        $(shared_read_writers!(@single $($item),+);)*
+    };
+
+    (@single $value_type:ty) => {
+        paste! {
+            shared_read_writers!(@single [<read_ $value_type>], [<write_ $value_type>], $value_type, $value_type);
+        }
+    };
+
+    (@single $value_type:ty, $address_type:ty) => {
+        paste! {
+            shared_read_writers!(@single [<read_ $value_type _from_ $address_type>],
+                                 [<write_ $value_type _to_ $address_type>], $value_type, $address_type);
+        }
+    };
+
+    (@single $read:ident, $write:ident, $value_type:ty, $address_type:ty) => {
+        #[must_use]
+        pub fn $read(&self, address: i32) -> $value_type {
+            <$address_type>::from_le_bytes(self.load(address)) as $value_type
+        }
+
+        pub fn $write(&self, address: i32, value: $value_type) {
+            self.store(address, (value as $address_type).to_le_bytes())
+        }
+    };
+}
+
+macro_rules! forward_atomics {
+    ($($name:ident($($argument:ident: $argument_type:ty),*) -> $result:ty, $width:literal;)*) => {
+        $(
+            pub fn $name(&self, address: i32, $($argument: $argument_type),*) -> $result {
+                // The inner memory spans the maximum, so only the bounds need checking here
+                self.checked_start(address, $width);
+                self.inner.memory.$name(address, $($argument),*)
+            }
+        )*
+    };
+}
+
+impl SharedLinearMemory {
+    /// Creates a shared memory of `pages` pages which may grow up to `maximum_pages`. The whole
+    /// maximum is reserved up front, with mmap the pages are only committed once touched.
+    pub fn new(pages: u32, maximum_pages: u32) -> Result<Self, MemoryError> {
+        if pages > maximum_pages {
+            return Err(MemoryError::LimitExceeded);
+        }
+        let mut memory = LinearMemory::try_new(maximum_pages)?;
+        let base = memory.descriptor().base;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                memory,
+                base,
+                length: AtomicUsize::new(pages as usize * PAGE_SIZE as usize),
+                maximum: maximum_pages as usize * PAGE_SIZE as usize,
+            }),
+        })
+    }
+
+    /// Current size of the memory in bytes.
+    pub fn size(&self) -> usize {
+        self.inner.length.load(Ordering::Acquire)
+    }
+
+    pub fn maximum(&self) -> usize {
+        self.inner.maximum
+    }
+
+    /// Grows the memory by `pages`, returning its previous size in pages like `memory.grow`. Other
+    /// threads may access the new pages as soon as this returns.
+    pub fn grow(&self, pages: u32) -> Result<u32, MemoryError> {
+        let delta = (pages as usize)
+            .checked_mul(PAGE_SIZE as usize)
+            .ok_or(MemoryError::SizeOverflow)?;
+        let previous = self
+            .inner
+            .length
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |length| {
+                length
+                    .checked_add(delta)
+                    .filter(|&length| length <= self.inner.maximum)
+            })
+            .map_err(|_| MemoryError::LimitExceeded)?;
+        Ok((previous / PAGE_SIZE as usize) as u32)
+    }
+
+    /// Start of an access of `byte_count` bytes, trapping unless it lies within the memory.
+    fn checked_start(&self, address: i32, byte_count: usize) -> usize {
+        usize::try_from(address)
+            .ok()
+            .filter(|start| {
+                start
+                    .checked_add(byte_count)
+                    .is_some_and(|end| end <= self.size())
+            })
+            .unwrap_or_else(|| trap::raise(Trap::OutOfBounds))
+    }
+
+    fn load<const N: usize>(&self, address: i32) -> [u8; N] {
+        let pointer = unsafe { self.inner.base.add(self.checked_start(address, N)) };
+        let mut bytes = [0; N];
+        unsafe {
+            match N {
+                8 if pointer.cast::<u64>().is_aligned() => bytes.copy_from_slice(
+                    &AtomicU64::from_ptr(pointer.cast())
+                        .load(Ordering::Relaxed)
+                        .to_ne_bytes(),
+                ),
+                4 if pointer.cast::<u32>().is_aligned() => bytes.copy_from_slice(
+                    &AtomicU32::from_ptr(pointer.cast())
+                        .load(Ordering::Relaxed)
+                        .to_ne_bytes(),
+                ),
+                2 if pointer.cast::<u16>().is_aligned() => bytes.copy_from_slice(
+                    &AtomicU16::from_ptr(pointer.cast())
+                        .load(Ordering::Relaxed)
+                        .to_ne_bytes(),
+                ),
+                _ => load_bytes(pointer, &mut bytes),
+            }
+        }
+        bytes
+    }
+
+    fn store<const N: usize>(&self, address: i32, bytes: [u8; N]) {
+        let pointer = unsafe { self.inner.base.add(self.checked_start(address, N)) };
+        unsafe {
+            match N {
+                8 if pointer.cast::<u64>().is_aligned() => AtomicU64::from_ptr(pointer.cast())
+                    .store(
+                        u64::from_ne_bytes(bytes[..].try_into().unwrap()),
+                        Ordering::Relaxed,
+                    ),
+                4 if pointer.cast::<u32>().is_aligned() => AtomicU32::from_ptr(pointer.cast())
+                    .store(
+                        u32::from_ne_bytes(bytes[..].try_into().unwrap()),
+                        Ordering::Relaxed,
+                    ),
+                2 if pointer.cast::<u16>().is_aligned() => AtomicU16::from_ptr(pointer.cast())
+                    .store(
+                        u16::from_ne_bytes(bytes[..].try_into().unwrap()),
+                        Ordering::Relaxed,
+                    ),
+                _ => store_bytes(pointer, &bytes),
+            }
+        }
+    }
+
+    shared_read_writers!(
+        (i32),
+        (i64),
+        (f32),
+        (f64),
+        (i32, i8),
+        (i32, i16),
+        (i32, u8),
+        (i32, u16),
+        (i64, i8),
+        (i64, i16),
+        (i64, i32),
+        (i64, u8),
+        (i64, u16),
+        (i64, u32),
+    );
+
+    /// Copies `buffer.len()` bytes starting at `address` out of the memory.
+    pub fn read_bytes(&self, address: i32, buffer: &mut [u8]) {
+        let start = self.checked_start(address, buffer.len());
+        unsafe { load_bytes(self.inner.base.add(start), buffer) };
+    }
+
+    pub fn write_bytes(&self, address: i32, bytes: &[u8]) {
+        let start = self.checked_start(address, bytes.len());
+        unsafe { store_bytes(self.inner.base.add(start), bytes) };
+    }
+
+    pub fn fill(&self, address: i32, byte_count: usize, value: u8) {
+        let start = self.checked_start(address, byte_count);
+        for offset in 0..byte_count {
+            unsafe { AtomicU8::from_ptr(self.inner.base.add(start + offset)) }
+                .store(value, Ordering::Relaxed);
+        }
+    }
+
+    forward_atomics!(
+        atomic_read_i32() -> i32, 4;
+        atomic_read_i64() -> i64, 8;
+        atomic_read_i32_from_i8() -> i32, 1;
+        atomic_read_i32_from_i16() -> i32, 2;
+        atomic_read_i32_from_u8() -> i32, 1;
+        atomic_read_i32_from_u16() -> i32, 2;
+        atomic_read_i64_from_i8() -> i64, 1;
+        atomic_read_i64_from_i16() -> i64, 2;
+        atomic_read_i64_from_i32() -> i64, 4;
+        atomic_read_i64_from_u8() -> i64, 1;
+        atomic_read_i64_from_u16() -> i64, 2;
+        atomic_read_i64_from_u32() -> i64, 4;
+        atomic_write_i32(value: i32) -> (), 4;
+        atomic_write_i64(value: i64) -> (), 8;
+        atomic_write_i32_to_i8(value: i32) -> (), 1;
+        atomic_write_i32_to_i16(value: i32) -> (), 2;
+        atomic_write_i32_to_u8(value: i32) -> (), 1;
+        atomic_write_i32_to_u16(value: i32) -> (), 2;
+        atomic_write_i64_to_i8(value: i64) -> (), 1;
+        atomic_write_i64_to_i16(value: i64) -> (), 2;
+        atomic_write_i64_to_i32(value: i64) -> (), 4;
+        atomic_write_i64_to_u8(value: i64) -> (), 1;
+        atomic_write_i64_to_u16(value: i64) -> (), 2;
+        atomic_write_i64_to_u32(value: i64) -> (), 4;
+        atomic_rmw_add_i32(value: i32) -> i32, 4;
+        atomic_rmw_sub_i32(value: i32) -> i32, 4;
+        atomic_rmw_and_i32(value: i32) -> i32, 4;
+        atomic_rmw_or_i32(value: i32) -> i32, 4;
+        atomic_rmw_xor_i32(value: i32) -> i32, 4;
+        atomic_rmw_exchange_i32(value: i32) -> i32, 4;
+        atomic_rmw_add_i32_to_i8(value: i32) -> i32, 1;
+        atomic_rmw_sub_i32_to_i8(value: i32) -> i32, 1;
+        atomic_rmw_and_i32_to_i8(value: i32) -> i32, 1;
+        atomic_rmw_or_i32_to_i8(value: i32) -> i32, 1;
+        atomic_rmw_xor_i32_to_i8(value: i32) -> i32, 1;
+        atomic_rmw_exchange_i32_to_i8(value: i32) -> i32, 1;
+        atomic_rmw_add_i32_to_i16(value: i32) -> i32, 2;
+        atomic_rmw_sub_i32_to_i16(value: i32) -> i32, 2;
+        atomic_rmw_and_i32_to_i16(value: i32) -> i32, 2;
+        atomic_rmw_or_i32_to_i16(value: i32) -> i32, 2;
+        atomic_rmw_xor_i32_to_i16(value: i32) -> i32, 2;
+        atomic_rmw_exchange_i32_to_i16(value: i32) -> i32, 2;
+        atomic_rmw_add_i64(value: i64) -> i64, 8;
+        atomic_rmw_sub_i64(value: i64) -> i64, 8;
+        atomic_rmw_and_i64(value: i64) -> i64, 8;
+        atomic_rmw_or_i64(value: i64) -> i64, 8;
+        atomic_rmw_xor_i64(value: i64) -> i64, 8;
+        atomic_rmw_exchange_i64(value: i64) -> i64, 8;
+        atomic_rmw_add_i64_to_i8(value: i64) -> i64, 1;
+        atomic_rmw_sub_i64_to_i8(value: i64) -> i64, 1;
+        atomic_rmw_and_i64_to_i8(value: i64) -> i64, 1;
+        atomic_rmw_or_i64_to_i8(value: i64) -> i64, 1;
+        atomic_rmw_xor_i64_to_i8(value: i64) -> i64, 1;
+        atomic_rmw_exchange_i64_to_i8(value: i64) -> i64, 1;
+        atomic_rmw_add_i64_to_i16(value: i64) -> i64, 2;
+        atomic_rmw_sub_i64_to_i16(value: i64) -> i64, 2;
+        atomic_rmw_and_i64_to_i16(value: i64) -> i64, 2;
+        atomic_rmw_or_i64_to_i16(value: i64) -> i64, 2;
+        atomic_rmw_xor_i64_to_i16(value: i64) -> i64, 2;
+        atomic_rmw_exchange_i64_to_i16(value: i64) -> i64, 2;
+        atomic_rmw_add_i64_to_i32(value: i64) -> i64, 4;
+        atomic_rmw_sub_i64_to_i32(value: i64) -> i64, 4;
+        atomic_rmw_and_i64_to_i32(value: i64) -> i64, 4;
+        atomic_rmw_or_i64_to_i32(value: i64) -> i64, 4;
+        atomic_rmw_xor_i64_to_i32(value: i64) -> i64, 4;
+        atomic_rmw_exchange_i64_to_i32(value: i64) -> i64, 4;
+        atomic_compare_exchange_i32(current: i32, new: i32) -> i32, 4;
+        atomic_compare_exchange_i32_to_i8(current: i32, new: i32) -> i32, 1;
+        atomic_compare_exchange_i32_to_i16(current: i32, new: i32) -> i32, 2;
+        atomic_compare_exchange_i64(current: i64, new: i64) -> i64, 8;
+        atomic_compare_exchange_i64_to_i8(current: i64, new: i64) -> i64, 1;
+        atomic_compare_exchange_i64_to_i16(current: i64, new: i64) -> i64, 2;
+        atomic_compare_exchange_i64_to_i32(current: i64, new: i64) -> i64, 4;
+        notify(count: i32) -> i32, 4;
+    );
+
+    pub fn atomic_fence(&self) {
+        self.inner.memory.atomic_fence();
+    }
+
+    #[cfg(feature = "threads")]
+    forward_atomics!(
+        wait_i32(expected: i32, timeout_nanos: i64) -> i32, 4;
+        wait_i64(expected: i64, timeout_nanos: i64) -> i32, 8;
+    );
+}
+
+unsafe fn load_bytes(source: *const u8, buffer: &mut [u8]) {
+    for (offset, byte) in buffer.iter_mut().enumerate() {
+        *byte =
+            unsafe { AtomicU8::from_ptr(source.add(offset).cast_mut()) }.load(Ordering::Relaxed);
+    }
+}
+
+unsafe fn store_bytes(destination: *mut u8, bytes: &[u8]) {
+    for (offset, &byte) in bytes.iter().enumerate() {
+        unsafe { AtomicU8::from_ptr(destination.add(offset)) }.store(byte, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::thread;
+
+    const PAGE: i32 = PAGE_SIZE as i32;
+
+    #[test]
+    fn test_shared_access_from_threads() {
+        let memory = SharedLinearMemory::new(1, 4).unwrap();
+
+        let threads: Vec<_> = (0..4)
+            .map(|index| {
+                let memory = memory.clone();
+                thread::spawn(move || {
+                    memory.write_i64(index * 8, index as i64);
+                    memory.write_i32_to_u16(64 + index * 2 + 1, 0xBEEF);
+                    memory.atomic_rmw_add_i32(128, 1);
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(memory.read_i64(24), 3);
+        assert_eq!(memory.atomic_read_i32(128), 4);
+        memory.write_bytes(200, b"shared");
+        let mut buffer = [0; 6];
+        memory.read_bytes(200, &mut buffer);
+        assert_eq!(&buffer, b"shared");
+    }
+
+    #[test]
+    fn test_shared_narrow_and_unaligned_atomics() {
+        let memory = SharedLinearMemory::new(1, 1).unwrap();
+
+        memory.atomic_write_i32_to_u16(2, 0xFFFF);
+        assert_eq!(memory.atomic_rmw_add_i32_to_i16(2, 1), -1);
+        assert_eq!(memory.atomic_read_i32_from_u16(2), 0);
+        assert_eq!(memory.atomic_compare_exchange_i64_to_i8(7, 0, 5), 0);
+        assert_eq!(memory.atomic_read_i64_from_u8(7), 5);
+        memory.atomic_fence();
+
+        let unaligned = |access: &dyn Fn()| {
+            let trapped = panic::catch_unwind(AssertUnwindSafe(access));
+            *trapped.unwrap_err().downcast::<Trap>().unwrap() == Trap::UnalignedAtomic
+        };
+        assert!(unaligned(&|| {
+            memory.atomic_rmw_add_i32(2, 1);
+        }));
+        assert!(unaligned(&|| {
+            memory.atomic_read_i64(4);
+        }));
+        assert!(unaligned(&|| {
+            memory.atomic_rmw_or_i64_to_i32(6, 1);
+        }));
+    }
+
+    #[test]
+    fn test_shared_grow() {
+        let memory = SharedLinearMemory::new(1, 2).unwrap();
+        let other = memory.clone();
+
+        let trapped = panic::catch_unwind(AssertUnwindSafe(|| other.write_i32(PAGE, 1)));
+        assert_eq!(
+            *trapped.unwrap_err().downcast::<Trap>().unwrap(),
+            Trap::OutOfBounds
+        );
+
+        assert_eq!(memory.grow(1).unwrap(), 1);
+        other.write_f64(PAGE + 3, 1.5);
+        assert_eq!(memory.read_f64(PAGE + 3), 1.5);
+        assert_eq!(memory.size(), 2 * PAGE as usize);
+        assert!(matches!(memory.grow(1), Err(MemoryError::LimitExceeded)));
+        assert!(SharedLinearMemory::new(3, 2).is_err());
+    }
+}
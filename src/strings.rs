@@ -0,0 +1,213 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::CStr;
+use core::fmt;
+use core::str::Utf8Error;
+
+use crate::memory::LinearMemory;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StringError {
+    OutOfBounds,
+    Unterminated,
+    InvalidUtf8(Utf8Error),
+    InvalidUtf16,
+}
+
+impl fmt::Display for StringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StringError::OutOfBounds => write!(f, "string exceeds memory bounds"),
+            StringError::Unterminated => write!(f, "string is not nul terminated"),
+            StringError::InvalidUtf8(error) => write!(f, "invalid utf-8: {error}"),
+            StringError::InvalidUtf16 => write!(f, "invalid utf-16"),
+        }
+    }
+}
+
+impl core::error::Error for StringError {}
+
+impl LinearMemory {
+    /// Reads the nul terminated string starting at `address`, the terminator included.
+    pub fn read_cstr(&self, address: i32) -> Result<&CStr, StringError> {
+        self.checked_range(address, 0)
+            .ok_or(StringError::OutOfBounds)?;
+
+        let null = self.find_null(address);
+        if null < 0 {
+            return Err(StringError::Unterminated);
+        }
+
+        let bytes = self.read_bytes(address, (null - address) as usize + 1);
+        CStr::from_bytes_with_nul(bytes).map_err(|_| StringError::Unterminated)
+    }
+
+    pub fn read_utf8(&self, address: i32, byte_count: usize) -> Result<&str, StringError> {
+        let bytes = self.read_string_bytes(address, byte_count)?;
+        core::str::from_utf8(bytes).map_err(StringError::InvalidUtf8)
+    }
+
+    /// Like `read_utf8` but replaces invalid sequences with U+FFFD.
+    pub fn read_utf8_lossy(
+        &self,
+        address: i32,
+        byte_count: usize,
+    ) -> Result<Cow<'_, str>, StringError> {
+        let bytes = self.read_string_bytes(address, byte_count)?;
+        Ok(String::from_utf8_lossy(bytes))
+    }
+
+    pub fn read_utf16(&self, address: i32, code_units: usize) -> Result<String, StringError> {
+        let units = self.read_code_units(address, code_units)?;
+        char::decode_utf16(units)
+            .collect::<Result<String, _>>()
+            .map_err(|_| StringError::InvalidUtf16)
+    }
+
+    /// Returns the little endian UTF-16 code units starting at `address` in host order.
+    pub fn read_code_units(
+        &self,
+        address: i32,
+        code_units: usize,
+    ) -> Result<impl Iterator<Item = u16> + '_, StringError> {
+        let byte_count = code_units
+            .checked_mul(size_of::<u16>())
+            .ok_or(StringError::OutOfBounds)?;
+        let bytes = self.read_string_bytes(address, byte_count)?;
+        Ok(bytes
+            .chunks_exact(size_of::<u16>())
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]])))
+    }
+
+    /// Encodes `value` as UTF-8 at `address`, returning the number of bytes written.
+    pub fn write_utf8(
+        &mut self,
+        address: i32,
+        value: &str,
+        nul_terminated: bool,
+    ) -> Result<usize, StringError> {
+        let byte_count = value.len() + nul_terminated as usize;
+        self.checked_range(address, byte_count)
+            .ok_or(StringError::OutOfBounds)?;
+
+        self.write_bytes(address, value.as_bytes());
+        if nul_terminated {
+            self.write_bytes(address + value.len() as i32, &[0]);
+        }
+        Ok(byte_count)
+    }
+
+    /// Encodes `value` as little endian UTF-16 at `address`, returning the number of code units
+    /// written.
+    pub fn write_utf16(
+        &mut self,
+        address: i32,
+        value: &str,
+        nul_terminated: bool,
+    ) -> Result<usize, StringError> {
+        let units: Vec<u16> = value.encode_utf16().collect();
+        self.write_code_units(address, &units, nul_terminated)
+    }
+
+    /// Writes host order UTF-16 code units to `address` in little endian order.
+    pub fn write_code_units(
+        &mut self,
+        address: i32,
+        units: &[u16],
+        nul_terminated: bool,
+    ) -> Result<usize, StringError> {
+        let unit_count = units.len() + nul_terminated as usize;
+        let byte_count = unit_count
+            .checked_mul(size_of::<u16>())
+            .ok_or(StringError::OutOfBounds)?;
+        self.checked_range(address, byte_count)
+            .ok_or(StringError::OutOfBounds)?;
+
+        let terminator = nul_terminated.then_some(0);
+        let bytes: Vec<u8> = units
+            .iter()
+            .copied()
+            .chain(terminator)
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        self.write_bytes(address, &bytes);
+        Ok(unit_count)
+    }
+
+    fn read_string_bytes(&self, address: i32, byte_count: usize) -> Result<&[u8], StringError> {
+        self.checked_range(address, byte_count)
+            .ok_or(StringError::OutOfBounds)?;
+        Ok(self.read_bytes(address, byte_count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_cstr() {
+        let mut memory = LinearMemory::new(1);
+        memory.write_bytes(100, b"hello\0world");
+
+        assert_eq!(memory.read_cstr(100).unwrap().to_bytes(), b"hello");
+        assert_eq!(memory.read_cstr(-1), Err(StringError::OutOfBounds));
+    }
+
+    #[test]
+    fn test_read_cstr_unterminated() {
+        let mut memory = LinearMemory::new(1);
+        let size = memory.size() as i32;
+        memory.fill(size - 4, 4, b'a');
+
+        assert_eq!(memory.read_cstr(size - 4), Err(StringError::Unterminated));
+    }
+
+    #[test]
+    fn test_utf8_round_trip() {
+        let mut memory = LinearMemory::new(1);
+
+        assert_eq!(memory.write_utf8(8, "héllo", true), Ok(7));
+        assert_eq!(memory.read_utf8(8, 6), Ok("héllo"));
+        assert_eq!(memory.read_cstr(8).unwrap().to_str(), Ok("héllo"));
+    }
+
+    #[test]
+    fn test_read_utf8_invalid() {
+        let mut memory = LinearMemory::new(1);
+        memory.write_bytes(0, &[b'a', 0xFF, b'b']);
+
+        assert!(matches!(
+            memory.read_utf8(0, 3),
+            Err(StringError::InvalidUtf8(_))
+        ));
+        assert_eq!(memory.read_utf8_lossy(0, 3).unwrap(), "a\u{FFFD}b");
+    }
+
+    #[test]
+    fn test_utf16_round_trip() {
+        let mut memory = LinearMemory::new(1);
+
+        assert_eq!(memory.write_utf16(32, "a😀", false), Ok(3));
+        let units: Vec<u16> = memory.read_code_units(32, 3).unwrap().collect();
+        assert_eq!(units, [0x61, 0xD83D, 0xDE00]);
+        assert_eq!(memory.read_utf16(32, 3).unwrap(), "a😀");
+        assert_eq!(memory.read_utf16(32, 2), Err(StringError::InvalidUtf16));
+    }
+
+    #[test]
+    fn test_write_out_of_bounds() {
+        let mut memory = LinearMemory::new(1);
+        let size = memory.size() as i32;
+
+        assert_eq!(
+            memory.write_utf8(size - 2, "ab", true),
+            Err(StringError::OutOfBounds)
+        );
+        assert_eq!(
+            memory.write_utf16(size - 2, "a", true),
+            Err(StringError::OutOfBounds)
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use core::fmt;
+
+/// Reasons an instruction can trap instead of completing.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trap {
+    OutOfBounds = 1,
+    UnalignedAtomic = 2,
+    InvalidOpcode = 3,
+    /// The access touched a page whose protection forbids it
+    Protected = 4,
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trap::OutOfBounds => write!(f, "out of bounds memory access"),
+            Trap::UnalignedAtomic => write!(f, "unaligned atomic"),
+            Trap::InvalidOpcode => write!(f, "invalid opcode"),
+            Trap::Protected => write!(f, "access to protected memory"),
+        }
+    }
+}
+
+impl core::error::Error for Trap {}
+
+/// Raises `trap` from inside an access. With `std` the trap itself is the panic payload, so
+/// embedders catching the unwind can recover it.
+#[cold]
+pub(crate) fn raise(trap: Trap) -> ! {
+    #[cfg(any(feature = "std", test))]
+    std::panic::panic_any(trap);
+    #[cfg(not(any(feature = "std", test)))]
+    panic!("{trap}");
+}
@@ -0,0 +1,343 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use dashmap::DashMap;
+use parking_lot::{Condvar, Mutex};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::memory::LinearMemory;
+use crate::schedule::DeterministicScheduler;
+
+/// Result of a wait which was woken by `notify`.
+pub const WAIT_OK: i32 = 0;
+/// Result of a wait whose address did not hold the expected value.
+pub const WAIT_NOT_EQUAL: i32 = 1;
+/// Result of a wait which ran out of time, or was cancelled.
+pub const WAIT_TIMED_OUT: i32 = 2;
+/// Result of every wait once `LinearMemory::terminate_waiters` has run.
+pub const WAIT_TERMINATED: i32 = 3;
+
+type WaitCallback = Box<dyn FnOnce(i32) + Send>;
+
+enum Waiter {
+    Blocking,
+    Async(Option<Waker>),
+    Callback(Option<WaitCallback>),
+}
+
+struct WaitState {
+    result: Option<i32>,
+    waiter: Waiter,
+}
+
+/// One waiter queued on an address. Blocking, async and callback waiters share the queue so
+/// `notify` wakes them in the order they started waiting.
+pub(crate) struct WaitEntry {
+    state: Mutex<WaitState>,
+    condvar: Condvar,
+}
+
+impl WaitEntry {
+    fn new(waiter: Waiter) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(WaitState {
+                result: None,
+                waiter,
+            }),
+            condvar: Condvar::new(),
+        })
+    }
+
+    /// Completes the wait with `result`, false if it had already completed.
+    pub(crate) fn resolve(&self, result: i32) -> bool {
+        let mut state = self.state.lock();
+        if state.result.is_some() {
+            return false;
+        }
+        state.result = Some(result);
+        match &mut state.waiter {
+            Waiter::Blocking => {
+                self.condvar.notify_one();
+            }
+            Waiter::Async(waker) => {
+                let waker = waker.take();
+                drop(state);
+                if let Some(waker) = waker {
+                    waker.wake();
+                }
+            }
+            Waiter::Callback(callback) => {
+                let callback = callback.take();
+                drop(state);
+                if let Some(callback) = callback {
+                    callback(result);
+                }
+            }
+        }
+        true
+    }
+
+    pub(crate) fn result(&self) -> Option<i32> {
+        self.state.lock().result
+    }
+
+    /// Blocks until the wait completes or `deadline` passes.
+    fn block(&self, deadline: Option<Instant>) -> i32 {
+        let mut state = self.state.lock();
+        loop {
+            if let Some(result) = state.result {
+                return result;
+            }
+            match deadline {
+                Some(deadline) => {
+                    if self.condvar.wait_until(&mut state, deadline).timed_out()
+                        && state.result.is_none()
+                    {
+                        state.result = Some(WAIT_TIMED_OUT);
+                    }
+                }
+                None => self.condvar.wait(&mut state),
+            }
+        }
+    }
+}
+
+type WaitQueue = Arc<Mutex<VecDeque<Arc<WaitEntry>>>>;
+
+#[derive(Default)]
+pub(crate) struct WaitQueues {
+    queues: DashMap<i32, WaitQueue>,
+    terminated: AtomicBool,
+}
+
+impl WaitQueues {
+    /// Queues `entry` behind the pending waiters on `address`. Waits which timed out or were
+    /// cancelled are dropped from the queue here, so waits nobody notifies don't pile up.
+    fn push(&self, address: i32, entry: Arc<WaitEntry>) {
+        if self.is_terminated() {
+            entry.resolve(WAIT_TERMINATED);
+            return;
+        }
+        let queue = self.queues.entry(address).or_default().clone();
+        {
+            let mut queue = queue.lock();
+            queue.retain(|queued| queued.result().is_none());
+            queue.push_back(entry.clone());
+        }
+        // Checked after queueing so a concurrent terminate either drains the entry or is seen here
+        if self.is_terminated() {
+            entry.resolve(WAIT_TERMINATED);
+        }
+    }
+
+    pub(crate) fn is_terminated(&self) -> bool {
+        self.terminated.load(Ordering::SeqCst)
+    }
+
+    /// Completes every queued wait with `WAIT_TERMINATED`, as will every later one.
+    pub(crate) fn terminate(&self) {
+        self.terminated.store(true, Ordering::SeqCst);
+        // Collected first so callbacks can touch the queues without deadlocking on a shard or
+        // queue lock
+        let queues: Vec<_> = self
+            .queues
+            .iter()
+            .map(|queue| queue.value().clone())
+            .collect();
+        for queue in queues {
+            let entries = core::mem::take(&mut *queue.lock());
+            for entry in entries {
+                entry.resolve(WAIT_TERMINATED);
+            }
+        }
+    }
+
+    /// Blocks until notified or `deadline` passes, or until `scheduler` resumes the thread if it
+    /// is scheduled.
+    pub(crate) fn wait(
+        &self,
+        address: i32,
+        deadline: Option<Instant>,
+        scheduler: Option<&DeterministicScheduler>,
+    ) -> i32 {
+        let entry = WaitEntry::new(Waiter::Blocking);
+        self.push(address, entry.clone());
+        scheduler
+            .and_then(|scheduler| scheduler.block(entry.clone(), deadline.is_some()))
+            .unwrap_or_else(|| entry.block(deadline))
+    }
+
+    pub(crate) fn wait_async(&self, address: i32) -> Arc<WaitEntry> {
+        let entry = WaitEntry::new(Waiter::Async(None));
+        self.push(address, entry.clone());
+        entry
+    }
+
+    pub(crate) fn wait_callback(&self, address: i32, callback: WaitCallback) -> WaitHandle {
+        let entry = WaitEntry::new(Waiter::Callback(Some(callback)));
+        self.push(address, entry.clone());
+        WaitHandle(entry)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self, address: i32) -> usize {
+        self.queues
+            .get(&address)
+            .map_or(0, |queue| queue.lock().len())
+    }
+
+    /// Wakes up to `count` waiters on `address` in FIFO order, skipping any which already timed
+    /// out or were cancelled. Notifies from a thread `scheduler` runs wake the waiters it picks.
+    pub(crate) fn notify(
+        &self,
+        address: i32,
+        count: u32,
+        scheduler: Option<&DeterministicScheduler>,
+    ) -> u32 {
+        let Some(queue) = self.queues.get(&address).map(|queue| queue.clone()) else {
+            return 0;
+        };
+        let mut woken = 0;
+        // Entries are resolved with the queue unlocked, a callback may start another wait
+        if let Some(scheduler) = scheduler.filter(|scheduler| scheduler.is_scheduled()) {
+            let mut pending: Vec<_> = core::mem::take(&mut *queue.lock())
+                .into_iter()
+                .filter(|entry| entry.result().is_none())
+                .collect();
+            while woken < count && !pending.is_empty() {
+                let entry = pending.remove(scheduler.choose_waiter(address, pending.len()));
+                woken += entry.resolve(WAIT_OK) as u32;
+            }
+            queue.lock().extend(pending);
+            return woken;
+        }
+        while woken < count {
+            let Some(entry) = queue.lock().pop_front() else {
+                break;
+            };
+            woken += entry.resolve(WAIT_OK) as u32;
+        }
+        woken
+    }
+}
+
+/// Future returned by `LinearMemory::wait_async_i32` and `wait_async_i64`, resolving to one of the
+/// `WAIT_*` results. Dropping it before it resolves cancels the wait.
+#[must_use = "futures do nothing unless polled"]
+pub struct WaitAsync<'a> {
+    memory: &'a LinearMemory,
+    address: i32,
+    entry: Option<Arc<WaitEntry>>,
+}
+
+impl<'a> WaitAsync<'a> {
+    pub(crate) fn new(
+        memory: &'a LinearMemory,
+        address: i32,
+        entry: Option<Arc<WaitEntry>>,
+    ) -> Self {
+        Self {
+            memory,
+            address,
+            entry,
+        }
+    }
+}
+
+impl Future for WaitAsync<'_> {
+    type Output = i32;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<i32> {
+        let Some(entry) = &self.entry else {
+            return Poll::Ready(WAIT_NOT_EQUAL);
+        };
+        let mut state = entry.state.lock();
+        match state.result {
+            Some(result) => {
+                drop(state);
+                if result == WAIT_OK {
+                    self.memory.acquire_notify(self.address);
+                }
+                Poll::Ready(result)
+            }
+            None => {
+                state.waiter = Waiter::Async(Some(cx.waker().clone()));
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for WaitAsync<'_> {
+    fn drop(&mut self) {
+        if let Some(entry) = &self.entry {
+            entry.resolve(WAIT_TIMED_OUT);
+        }
+    }
+}
+
+/// Handle to a wait registered with a completion callback.
+pub struct WaitHandle(Arc<WaitEntry>);
+
+impl WaitHandle {
+    /// Cancels the wait, true if it was still pending and the callback will never run.
+    pub fn cancel(&self) -> bool {
+        let mut state = self.0.state.lock();
+        if state.result.is_some() {
+            return false;
+        }
+        state.result = Some(WAIT_TIMED_OUT);
+        state.waiter = Waiter::Callback(None);
+        true
+    }
+
+    /// Result the wait completed with, None while it is still pending.
+    pub fn result(&self) -> Option<i32> {
+        self.0.result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicI32, Ordering};
+    use std::task::Wake;
+
+    struct CountingWaker(AtomicI32);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_wait_async() {
+        let memory = LinearMemory::new(1);
+        memory.atomic_write_i32(8, 7);
+        let counter = Arc::new(CountingWaker(AtomicI32::new(0)));
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut not_equal = memory.wait_async_i32(8, 1);
+        assert_eq!(
+            Pin::new(&mut not_equal).poll(&mut cx),
+            Poll::Ready(WAIT_NOT_EQUAL)
+        );
+
+        let mut wait = memory.wait_async_i32(8, 7);
+        assert_eq!(Pin::new(&mut wait).poll(&mut cx), Poll::Pending);
+        assert_eq!(memory.notify(8, 1), 1);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        assert_eq!(Pin::new(&mut wait).poll(&mut cx), Poll::Ready(WAIT_OK));
+
+        // A dropped future no longer counts as a waiter
+        let mut cancelled = memory.wait_async_i64(8, 7);
+        assert_eq!(Pin::new(&mut cancelled).poll(&mut cx), Poll::Pending);
+        drop(cancelled);
+        assert_eq!(memory.notify(8, 1), 0);
+    }
+}
@@ -0,0 +1,208 @@
+use parking_lot::RwLock;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::memory::{AccessKind, Operation};
+
+pub type WatchpointId = u32;
+
+type WatchCallback = Arc<dyn Fn(&WatchEvent) + Send + Sync>;
+
+/// Describes an access that touched a watched range. For reads `old` and `new` are identical.
+#[derive(Debug)]
+pub struct WatchEvent<'a> {
+    pub address: i32,
+    pub old: &'a [u8],
+    pub new: &'a [u8],
+    pub operation: Operation,
+}
+
+struct Watchpoint {
+    id: WatchpointId,
+    range: Range<usize>,
+    kind: AccessKind,
+    callback: WatchCallback,
+}
+
+#[derive(Default)]
+pub(crate) struct Watchpoints {
+    entries: RwLock<Vec<Watchpoint>>,
+    active: AtomicBool,
+    next_id: AtomicU32,
+}
+
+impl Watchpoints {
+    pub(crate) fn add(
+        &self,
+        range: Range<i32>,
+        kind: AccessKind,
+        callback: WatchCallback,
+    ) -> WatchpointId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut entries = self.entries.write();
+        entries.push(Watchpoint {
+            id,
+            range: range.start as usize..range.end as usize,
+            kind,
+            callback,
+        });
+        self.active.store(true, Ordering::Release);
+        id
+    }
+
+    pub(crate) fn remove(&self, id: WatchpointId) -> bool {
+        let mut entries = self.entries.write();
+        let before = entries.len();
+        entries.retain(|watchpoint| watchpoint.id != id);
+        self.active.store(!entries.is_empty(), Ordering::Release);
+        entries.len() != before
+    }
+
+    #[inline(always)]
+    pub(crate) fn is_active(&self) -> bool {
+        self.active.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn observe<'a, R>(
+        &self,
+        address: i32,
+        byte_count: usize,
+        kind: AccessKind,
+        operation: Operation,
+        bytes: impl Fn() -> &'a [u8],
+        access: impl FnOnce() -> R,
+    ) -> R {
+        let start = address as usize;
+        let end = start + byte_count;
+
+        // Callbacks are collected up front so they may add or remove watchpoints themselves
+        let callbacks: Vec<WatchCallback> = self
+            .entries
+            .read()
+            .iter()
+            .filter(|watchpoint| {
+                watchpoint.kind.overlaps(kind)
+                    && watchpoint.range.start < end
+                    && start < watchpoint.range.end
+            })
+            .map(|watchpoint| watchpoint.callback.clone())
+            .collect();
+
+        if callbacks.is_empty() {
+            return access();
+        }
+
+        let old = bytes().to_vec();
+        let result = access();
+        let event = WatchEvent {
+            address,
+            old: &old,
+            new: bytes(),
+            operation,
+        };
+        for callback in callbacks {
+            callback(&event);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memory::{AccessKind, LinearMemory, Operation};
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    type Recorded = Arc<Mutex<Vec<(i32, Vec<u8>, Vec<u8>, Operation)>>>;
+
+    fn record(memory: &LinearMemory, range: std::ops::Range<i32>, kind: AccessKind) -> Recorded {
+        let events: Recorded = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        memory.add_watchpoint(range, kind, move |event| {
+            sink.lock().push((
+                event.address,
+                event.old.to_vec(),
+                event.new.to_vec(),
+                event.operation,
+            ));
+        });
+        events
+    }
+
+    #[test]
+    fn test_watchpoint_write() {
+        let mut memory = LinearMemory::new(1);
+        memory.write_i32(16, 7);
+        let events = record(&memory, 16..20, AccessKind::Write);
+
+        memory.write_i32(16, 9);
+        memory.write_i32(32, 1);
+        let _ = memory.read_i32(16);
+
+        let events = events.lock();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0],
+            (
+                16,
+                7i32.to_le_bytes().to_vec(),
+                9i32.to_le_bytes().to_vec(),
+                Operation::Store
+            )
+        );
+    }
+
+    #[test]
+    fn test_watchpoint_read() {
+        let mut memory = LinearMemory::new(1);
+        memory.write_i64(8, 3);
+        let events = record(&memory, 12..13, AccessKind::Read);
+
+        assert_eq!(memory.read_i64(8), 3);
+        memory.write_i64(8, 4);
+
+        let events = events.lock();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, 8);
+        assert_eq!(events[0].1, events[0].2);
+        assert_eq!(events[0].3, Operation::Load);
+    }
+
+    #[test]
+    fn test_watchpoint_atomic_and_bulk() {
+        let mut memory = LinearMemory::new(1);
+        let events = record(&memory, 0..64, AccessKind::ReadWrite);
+
+        memory.atomic_rmw_add_i32(0, 5);
+        memory.fill(8, 4, 0xAA);
+        memory.write_bytes(32, &[1, 2, 3]);
+
+        let events = events.lock();
+        let operations: Vec<Operation> = events.iter().map(|event| event.3).collect();
+        assert_eq!(
+            operations,
+            [Operation::AtomicRmw, Operation::Fill, Operation::WriteBytes]
+        );
+        assert_eq!(events[0].2, 5i32.to_le_bytes());
+        assert_eq!(events[1].1, [0; 4]);
+        assert_eq!(events[1].2, [0xAA; 4]);
+    }
+
+    #[test]
+    fn test_remove_watchpoint() {
+        let mut memory = LinearMemory::new(1);
+        let events: Recorded = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let id = memory.add_watchpoint(0..4, AccessKind::Write, move |event| {
+            sink.lock()
+                .push((event.address, Vec::new(), Vec::new(), event.operation));
+        });
+
+        assert!(memory.remove_watchpoint(id));
+        assert!(!memory.remove_watchpoint(id));
+        memory.write_i32(0, 1);
+
+        assert!(events.lock().is_empty());
+    }
+}